@@ -1,3 +1,3 @@
-from .simular import PyEvm, PyAbi, TxResult
+from .simular import PyEvm, PyAbi, TxResult, RevertError, OutOfGasError
 from .contract import Contract
 from .utils import *
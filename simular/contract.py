@@ -23,6 +23,20 @@ def convert_for_soltypes(args: typing.Tuple) -> str:
     return clean(args)
 
 
+def salt_to_hex(salt: typing.Union[int, str, bytes]) -> str:
+    """
+    Convert a CREATE2 salt to 32 bytes of hex. Shorter salts are left padded with zeros
+    """
+    if isinstance(salt, int):
+        return f"0x{salt:064x}"
+    if isinstance(salt, (bytes, bytearray)):
+        salt = salt.hex()
+    salt = salt.removeprefix("0x")
+    if len(salt) > 64:
+        raise Exception("'salt' must be at most 32 bytes")
+    return "0x" + salt.rjust(64, "0")
+
+
 class Function:
     """
     Contains information needed to interact with a contract function. This
@@ -35,59 +49,264 @@ class Function:
         self.abi = abi
         self.contract_address = contract_address
 
-    def call(self, *args) -> typing.Any:
+    def call(self, *args, caller: str = None) -> typing.Any:
         """
         Make a read-only call to the contract, returning any results. Solidity
         read-only calls are marked as `view` or `pure`. Does not commit any state
         changes to the Evm.
 
         - `args`: 0 or more expected arguments to the function
+        - `caller`: optional address of the caller. This translates to `msg.sender` in Solidity
 
         Returns: the decoded result
         """
         if not self.contract_address:
             raise Exception("missing contract address. see at() method")
 
+        if caller is not None and not is_address(caller):
+            raise Exception("caller is not a valid address")
+
         stargs = convert_for_soltypes(args)
-        result = self.evm.call(self.name, stargs, self.contract_address, self.abi)
+        result = self.evm.call(
+            self.name, stargs, self.contract_address, self.abi, caller=caller
+        )
         return result
 
-    def simulate(self, *args, caller: str = None, value: int = 0) -> "TxResult":
+    def simulate(
+        self,
+        *args,
+        caller: str = None,
+        value: int = 0,
+        gas_limit: int = None,
+        gas_price: int = None,
+        max_fee_per_gas: int = None,
+        max_priority_fee_per_gas: int = None,
+        access_list: typing.List[typing.Dict[str, typing.Any]] = None,
+    ) -> "TxResult":
         """
         Simulate a write call to the contract w/o changing state.
         """
         if not self.contract_address:
             raise Exception("missing contract address. see at() method")
 
+        # use the impersonated caller, see PyEvm.start_prank
+        caller = caller or self.evm.get_prank()
         if not is_address(caller):
             raise Exception("caller is missing or is not a valid address")
 
         stargs = convert_for_soltypes(args)
         result = self.evm.simulate(
-            self.name, stargs, caller, self.contract_address, value, self.abi
+            self.name,
+            stargs,
+            caller,
+            self.contract_address,
+            value,
+            self.abi,
+            gas_limit=gas_limit,
+            gas_price=gas_price,
+            max_fee_per_gas=max_fee_per_gas,
+            max_priority_fee_per_gas=max_priority_fee_per_gas,
+            access_list=access_list,
         )
         return result
 
-    def transact(self, *args, caller: str = None, value: int = 0) -> "TxResult":
+    def transact(
+        self,
+        *args,
+        caller: str = None,
+        value: int = 0,
+        gas_limit: int = None,
+        gas_price: int = None,
+        max_fee_per_gas: int = None,
+        max_priority_fee_per_gas: int = None,
+        access_list: typing.List[typing.Dict[str, typing.Any]] = None,
+    ) -> "TxResult":
         """
         Make a write call to the contract changing the state of the Evm.
         - `args`: 0 or more expected arguments to the function
         - `caller`: the address of the caller. This translates to `msg.sender` in a Solidity
         - `value` : an optional amount of Ether to send with the value ... `msg.value`
+        - `gas_limit`: optional gas limit for the transaction. Default: the block gas limit
+        - `gas_price`: optional price per unit of gas for a legacy transaction
+        - `max_fee_per_gas`: optional max fee per unit of gas (EIP-1559)
+        - `max_priority_fee_per_gas`: optional max tip per unit of gas (EIP-1559)
+        - `access_list`: optional EIP-2930 access list. See `create_access_list`
         Returns: the decoded result
         """
         if not self.contract_address:
             raise Exception("missing contract address. see at() method")
 
+        # use the impersonated caller, see PyEvm.start_prank
+        caller = caller or self.evm.get_prank()
         if not is_address(caller):
             raise Exception("caller is missing or is not a valid address")
 
         stargs = convert_for_soltypes(args)
         result = self.evm.transact(
-            self.name, stargs, caller, self.contract_address, value, self.abi
+            self.name,
+            stargs,
+            caller,
+            self.contract_address,
+            value,
+            self.abi,
+            gas_limit=gas_limit,
+            gas_price=gas_price,
+            max_fee_per_gas=max_fee_per_gas,
+            max_priority_fee_per_gas=max_priority_fee_per_gas,
+            access_list=access_list,
         )
         return result
 
+    def estimate_gas(self, *args, caller: str = None, value: int = 0) -> int:
+        """
+        Estimate the gas limit needed for a write call to succeed w/o changing state.
+        - `args`: 0 or more expected arguments to the function
+        - `caller`: the address of the caller
+        - `value` : an optional amount of Ether to send with the call
+        Returns: the gas limit
+        """
+        if not self.contract_address:
+            raise Exception("missing contract address. see at() method")
+
+        caller = caller or self.evm.get_prank()
+        if not is_address(caller):
+            raise Exception("caller is missing or is not a valid address")
+
+        stargs = convert_for_soltypes(args)
+        return self.evm.estimate_gas(
+            self.name, stargs, caller, self.contract_address, value, self.abi
+        )
+
+    def create_access_list(
+        self, *args, caller: str = None, value: int = 0
+    ) -> typing.Dict[str, typing.Any]:
+        """
+        Generate an EIP-2930 access list for a write call w/o changing state.
+        - `args`: 0 or more expected arguments to the function
+        - `caller`: the address of the caller
+        - `value` : an optional amount of Ether to send with the call
+        Returns: a dict with the `access_list`, `gas_used` (with the access list),
+        `gas_used_without_access_list` and `error`: why the call failed, or None
+        """
+        if not self.contract_address:
+            raise Exception("missing contract address. see at() method")
+
+        caller = caller or self.evm.get_prank()
+        if not is_address(caller):
+            raise Exception("caller is missing or is not a valid address")
+
+        stargs = convert_for_soltypes(args)
+        return self.evm.create_access_list(
+            self.name, stargs, caller, self.contract_address, value, self.abi
+        )
+
+    def send(
+        self,
+        *args,
+        caller: str = None,
+        value: int = 0,
+        gas_limit: int = None,
+        gas_price: int = None,
+        max_fee_per_gas: int = None,
+        max_priority_fee_per_gas: int = None,
+    ) -> str:
+        """
+        Queue a write call in the mempool. It's executed when the next block is mined.
+        See `PyEvm.mine_block`.
+        - `args`: 0 or more expected arguments to the function
+        - `caller`: the address of the caller
+        - `value` : an optional amount of Ether to send with the call
+        - `gas_limit`, `gas_price`, `max_fee_per_gas`, `max_priority_fee_per_gas`: see `transact`
+        Returns: the transaction hash
+        """
+        if not self.contract_address:
+            raise Exception("missing contract address. see at() method")
+
+        caller = caller or self.evm.get_prank()
+        if not is_address(caller):
+            raise Exception("caller is missing or is not a valid address")
+
+        stargs = convert_for_soltypes(args)
+        return self.evm.send_transaction(
+            self.name,
+            stargs,
+            caller,
+            self.contract_address,
+            value,
+            self.abi,
+            gas_limit=gas_limit,
+            gas_price=gas_price,
+            max_fee_per_gas=max_fee_per_gas,
+            max_priority_fee_per_gas=max_priority_fee_per_gas,
+        )
+
+    def build_transaction(
+        self, *args, caller: str = None, value: int = 0, gas_limit: int = None
+    ) -> typing.Dict[str, typing.Any]:
+        """
+        Build a write call for `PyEvm.transact_bundle` or `PyEvm.simulate_bundle`.
+        - `args`: 0 or more expected arguments to the function
+        - `caller`: the address of the caller
+        - `value` : an optional amount of Ether to send with the call
+        - `gas_limit`: optional gas limit for the transaction
+        Returns: the transaction as a dict
+        """
+        if not self.contract_address:
+            raise Exception("missing contract address. see at() method")
+
+        caller = caller or self.evm.get_prank()
+        if not is_address(caller):
+            raise Exception("caller is missing or is not a valid address")
+
+        return {
+            "fn_name": self.name,
+            "args": convert_for_soltypes(args),
+            "caller": caller,
+            "to": self.contract_address,
+            "abi": self.abi,
+            "value": value,
+            "gas_limit": gas_limit,
+        }
+
+    def debug_trace(
+        self,
+        *args,
+        caller: str = None,
+        value: int = 0,
+        enable_memory: bool = False,
+        disable_stack: bool = False,
+        disable_storage: bool = False,
+    ) -> typing.Dict[str, typing.Any]:
+        """
+        Trace each opcode executed by a write call w/o changing state.  Only calls
+        are traced, not committed transactions; to compare with a node's trace of a
+        transaction, fork at the block before it and trace the same call.
+        - `args`: 0 or more expected arguments to the function
+        - `caller`: the address of the caller
+        - `value` : an optional amount of Ether to send with the call
+        - `enable_memory`, `disable_stack`, `disable_storage`: same as geth's struct logger options
+        Returns: a dict in the same format as geth's `debug_traceTransaction`
+        """
+        if not self.contract_address:
+            raise Exception("missing contract address. see at() method")
+
+        caller = caller or self.evm.get_prank()
+        if not is_address(caller):
+            raise Exception("caller is missing or is not a valid address")
+
+        stargs = convert_for_soltypes(args)
+        return self.evm.debug_trace_call(
+            self.name,
+            stargs,
+            caller,
+            self.contract_address,
+            value,
+            self.abi,
+            enable_memory=enable_memory,
+            disable_stack=disable_stack,
+            disable_storage=disable_storage,
+        )
+
 
 class Contract:
     def __init__(self, evm: PyEvm, abi: PyAbi):
@@ -135,14 +354,32 @@ class Contract:
         self.address = address
         return self
 
-    def deploy(self, *args, caller: str = None, value: int = 0) -> str:
+    def deploy(
+        self,
+        *args,
+        caller: str = None,
+        value: int = 0,
+        gas_limit: int = None,
+        gas_price: int = None,
+        max_fee_per_gas: int = None,
+        max_priority_fee_per_gas: int = None,
+        salt: typing.Union[int, str, bytes] = None,
+    ) -> str:
         """
         Deploy the contract, returning it's deployed address
         - `args`: a list of args (if any)
         - `caller`: the address of the requester...`msg.sender`
         - `value`: optional amount of Ether for the contract
+        - `gas_limit`: optional gas limit for the transaction
+        - `gas_price`, `max_fee_per_gas`, `max_priority_fee_per_gas`: optional gas pricing.
+          See `Function.transact`
+        - `salt`: optional 32 byte salt (int, hex string or bytes). If set, the contract
+          is deployed with CREATE2 through the deterministic deployment factory, so the
+          address only depends on the salt and the init code
         Returns the address of the deployed contract
         """
+        # use the impersonated caller, see PyEvm.start_prank
+        caller = caller or self.evm.get_prank()
         if not caller:
             raise Exception("Missing required 'caller' address")
 
@@ -150,6 +387,46 @@ class Contract:
             raise Exception("'caller' is not a valid ethereum address")
 
         stargs = convert_for_soltypes(args)
-        addr = self.evm.deploy(stargs, caller, value, self.abi)
+        gas = dict(
+            gas_limit=gas_limit,
+            gas_price=gas_price,
+            max_fee_per_gas=max_fee_per_gas,
+            max_priority_fee_per_gas=max_priority_fee_per_gas,
+        )
+        if salt is None:
+            addr = self.evm.deploy(stargs, caller, value, self.abi, **gas)
+        else:
+            addr = self.evm.deploy_create2(
+                stargs, caller, salt_to_hex(salt), value, self.abi, **gas
+            )
         self.address = addr
         return addr
+
+    def deploy_at(
+        self, address: str, *args, caller: str = None, value: int = 0
+    ) -> str:
+        """
+        Deploy the contract at `address`, returning the address.  The constructor is
+        run at `address`, so `address(this)`, immutables and storage are as if the
+        contract was created there.  No transaction is recorded, and the nonce of the
+        caller isn't used.  While the constructor runs, the code size of `address` is
+        the size of the init code, not 0.  Fails if the constructor returns no code.
+        - `address`: where to deploy the contract. It must not have code
+        - `args`: a list of args (if any)
+        - `caller`: the address of the requester...`msg.sender` in the constructor
+        - `value`: optional amount of Ether for the contract, paid by the caller
+        """
+        # use the impersonated caller, see PyEvm.start_prank
+        caller = caller or self.evm.get_prank()
+        if not caller:
+            raise Exception("Missing required 'caller' address")
+
+        if not is_address(caller):
+            raise Exception("'caller' is not a valid ethereum address")
+
+        if not is_address(address):
+            raise Exception("'address' is not a valid ethereum address")
+
+        stargs = convert_for_soltypes(args)
+        self.address = self.evm.deploy_at(address, stargs, caller, value, self.abi)
+        return self.address
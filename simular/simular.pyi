@@ -1,4 +1,28 @@
-from typing import Optional, Type, List, Tuple, Any, Dict
+from typing import Optional, Type, List, Tuple, Any, Dict, Union, Callable
+
+class RevertError(Exception):
+    """
+    Raised when a transaction reverts
+    """
+
+    name: Optional[str]
+    """the name of the error, e.g. `Error`, `Panic` or the name of a custom error in the ABI"""
+    error_args: Optional[Tuple[Any, ...]]
+    """the decoded arguments of the error"""
+    data: bytes
+    """the raw revert data"""
+    gas_used: int
+    """gas used by the transaction"""
+
+class OutOfGasError(Exception):
+    """
+    Raised when a transaction runs out of gas
+    """
+
+    gas_limit: int
+    """the gas limit of the transaction"""
+    gas_used: int
+    """gas used by the transaction"""
 
 class TxResult:
     @property
@@ -21,29 +45,87 @@ class TxResult:
         Return the amount of gas used by the transaction
         """
 
+    @property
+    def gas_refunded(self) -> int:
+        """
+        Return the amount of gas refunded to the caller
+        """
+
+    @property
+    def effective_gas_price(self) -> int:
+        """
+        Return the price paid per unit of gas
+        """
+
+    @property
+    def fee(self) -> int:
+        """
+        Return the total fee paid by the caller: `gas_used * effective_gas_price`
+        """
+
+    @property
+    def console_logs(self) -> List[str]:
+        """
+        Return the messages logged with Hardhat's `console.log`. See `PyEvm.set_console_logs`
+        """
+
+    @property
+    def trace(self) -> Optional[Dict[str, Any]]:
+        """
+        Return the tree of calls made by the transaction if call tracing is enabled.
+        See `PyEvm.set_call_tracing`.  Each call is a dict with the keys:
+        `kind`, `from`, `to`, `value`, `input`, `output`, `gas_limit`, `gas_used`,
+        `success`, `error`, and `calls` (a list of nested calls)
+        """
+
+    @property
+    def tx_hash(self) -> Optional[str]:
+        """
+        Return the hash of the transaction if it was committed. See `PyEvm.get_receipt`
+        """
+
 class PyEvm:
-    def __new__(cls: Type["PyEvm"]) -> "PyEvm":
+    def __new__(cls: Type["PyEvm"], spec: Optional[str] = None) -> "PyEvm":
         """
         Create an instance of the Evm using In-memory storage
+
+        - `spec`: optional name of the hardfork to use, e.g. 'shanghai', 'cancun'. Default is 'latest'
         """
 
     @staticmethod
     def from_fork(
-        cls: Type["PyEvm"], url: str, blocknumber: Optional[int] = None
+        cls: Type["PyEvm"],
+        url: str,
+        blocknumber: Optional[int] = None,
+        spec: Optional[str] = None,
     ) -> "PyEvm":
         """
         Create an EVM configured to use a remote node to load state data.
 
         - `url`: the URL of the remote node to connect to
         - `blockchain`: optional block to start.  Default is 'latest'
+        - `spec`: optional name of the hardfork. Default is the hardfork active at the block
         """
 
     @staticmethod
-    def from_snapshot(raw: str) -> "PyEvm":
+    def from_snapshot(raw: str, spec: Optional[str] = None) -> "PyEvm":
         """
         Create an EVM loading state from a snapshot.
 
         - `raw`: the snapshot data
+        - `spec`: optional name of the hardfork. Default is the hardfork saved in the snapshot
+        """
+
+    def get_spec(self) -> str:
+        """
+        Return the name of the hardfork used by the EVM
+        """
+
+    def set_spec(self, spec: str):
+        """
+        Change the hardfork used by the EVM.
+
+        - `spec`: name of the hardfork, e.g. 'shanghai', 'cancun', 'latest'
         """
 
     def create_snapshot(self) -> str:
@@ -51,6 +133,180 @@ class PyEvm:
         Create a snapshot by saving EVM state to str.
         """
 
+    def get_block_config(self) -> Dict[str, Any]:
+        """
+        Return the block configuration as a dict with the keys:
+        `chain_id`, `coinbase`, `gas_limit`, `basefee`, `prevrandao`, `difficulty`
+        """
+
+    def set_block_config(
+        self,
+        chain_id: Optional[int] = None,
+        coinbase: Optional[str] = None,
+        gas_limit: Optional[int] = None,
+        basefee: Optional[int] = None,
+        prevrandao: Optional[str] = None,
+        difficulty: Optional[int] = None,
+    ):
+        """
+        Change the values seen by contracts through `block.*` and `block.chainid`.
+        Only the values provided are changed.
+
+        - `chain_id`: `block.chainid`
+        - `coinbase`: address of the block beneficiary, `block.coinbase`
+        - `gas_limit`: `block.gaslimit`
+        - `basefee`: `block.basefee` in wei
+        - `prevrandao`: hex encoded 32 bytes, `block.prevrandao`
+        - `difficulty`: `block.difficulty` (before the merge)
+        """
+
+    def set_call_tracing(self, enabled: bool):
+        """
+        Enable or disable the call tracer. When enabled, `TxResult.trace` contains
+        the tree of calls made by a transaction.
+        """
+
+    def get_call_tracing(self) -> bool:
+        """
+        Return True if the call tracer is enabled
+        """
+
+    def set_console_logs(self, enabled: bool, print: bool = False):
+        """
+        Enable or disable capturing messages logged with Hardhat's `console.log` in
+        `TxResult.console_logs`. Disabled by default.
+
+        - `print`: optional. also print the messages to stdout, including those of
+           transactions that fail. Default: False
+        """
+
+    def get_console_logs(self) -> bool:
+        """
+        Return True if `console.log` messages are captured
+        """
+
+    def set_enforce_block_gas_limit(self, enabled: bool):
+        """
+        Enable or disable the block gas limit. When enabled, a transaction with a
+        gas limit above the gas remaining in the block fails. Default: disabled
+        """
+
+    def get_enforce_block_gas_limit(self) -> bool:
+        """
+        Return True if the block gas limit is enforced
+        """
+
+    def set_gas_profiling(self, enabled: bool):
+        """
+        Enable or disable the gas profiler. When enabled, the gas used by each
+        transaction is added to the report returned by `get_gas_report`
+        """
+
+    def get_gas_profiling(self) -> bool:
+        """
+        Return True if the gas profiler is enabled
+        """
+
+    def reset_gas_report(self):
+        """
+        Clear the gas report
+        """
+
+    def get_gas_report(self, abis: Optional[Dict[str, PyAbi]] = None) -> Dict[str, Any]:
+        """
+        Return the gas used by the transactions run while the profiler was enabled.
+
+        - `abis`: optional map of contract address to `PyAbi` used to resolve function names
+
+        Returns a dict with the keys:
+        - `functions`: a list of dicts with `address`, `selector`, `name`, `calls`,
+          `min`, `avg`, `median`, `max` and `total`
+        - `deployments`: a list of dicts with `address`, `gas` and `size`
+        - `opcodes`: a dict of category (`SLOAD`, `SSTORE`, `CALL`, `LOG`, `MEMORY`, `OTHER`)
+          to a dict with `count` and `gas`
+        """
+
+    def set_coverage(self, enabled: bool):
+        """
+        Enable or disable coverage. When enabled, the instructions and branches executed
+        by each transaction are recorded for `coverage_lcov`.  Constructors aren't covered
+        """
+
+    def get_coverage(self) -> bool:
+        """
+        Return True if coverage is enabled
+        """
+
+    def reset_coverage(self):
+        """
+        Clear the coverage
+        """
+
+    def coverage_lcov(self, contracts: List[Dict[str, Any]]) -> str:
+        """
+        Return an LCOV report of the lines and branches of the `contracts` executed
+        while coverage was enabled. Each contract is a dict with:
+        - `address` or `bytecode`: where the contract is deployed, or its runtime bytecode
+        - `source_map`: the source map of the runtime bytecode from solc
+          (`evm.deployedBytecode.sourceMap`)
+        - `sources`: a dict of source id to a tuple of `(path, content)`
+        """
+
+    def enable_fee_market(
+        self, elasticity_multiplier: int = 2, base_fee_max_change_denominator: int = 8
+    ):
+        """
+        Enable the EIP-1559 fee market. Each time the block is advanced, `block.basefee`
+        is adjusted based on the gas used in the previous block.
+
+        - `elasticity_multiplier`: the gas target is `gas_limit / elasticity_multiplier`. Default: 2
+        - `base_fee_max_change_denominator`: the max change per block is `basefee / base_fee_max_change_denominator`. Default: 8
+        """
+
+    def disable_fee_market(self):
+        """
+        Disable the EIP-1559 fee market. The base fee stays at its current value
+        """
+
+    def get_fee_market(self) -> Optional[Dict[str, int]]:
+        """
+        Return the fee market settings or None if the fee market is disabled
+        """
+
+    def get_basefee(self) -> int:
+        """
+        Return the current `block.basefee`
+        """
+
+    def get_block_gas_used(self) -> int:
+        """
+        Return the amount of gas used by transactions in the current block
+        """
+
+    def checkpoint(self) -> int:
+        """
+        Save the current state in memory. Much faster than `create_snapshot`.
+        Checkpoints can be nested.
+
+        Returns the id of the checkpoint
+        """
+
+    def revert_to(self, id: int) -> bool:
+        """
+        Restore the state saved by checkpoint `id`, including the transactions,
+        receipts, blocks and mempool. The checkpoint, and any checkpoints created
+        after it, are removed.
+
+        Returns False if the checkpoint doesn't exist
+        """
+
+    def discard(self, id: int) -> bool:
+        """
+        Remove the checkpoint `id` without changing the current state.
+
+        Returns False if the checkpoint doesn't exist
+        """
+
     def create_account(self, address: str, balance: Optional[int] = 0):
         """
         Create an account.
@@ -64,18 +320,252 @@ class PyEvm:
         Return the balance of the given user. Where 'user' is the address.
         """
 
-    def transfer(self, caller: str, to: str, amount: int):
+    def start_prank(self, caller: str):
+        """
+        Impersonate `caller`. All transactions, including read calls, use `caller` for
+        `msg.sender` and `tx.origin` until `stop_prank` is called.  This overrides
+        the caller passed to a transaction.
+        """
+
+    def stop_prank(self):
+        """
+        Stop impersonating the caller set by `start_prank`
+        """
+
+    def get_prank(self) -> Optional[str]:
+        """
+        Return the address being impersonated, if any
+        """
+
+    def set_balance(self, address: str, balance: int):
+        """
+        Set the balance of an account. Other account fields are not changed.
+
+        - `address`: the address of the account
+        - `balance`: the new balance in Wei
+        """
+
+    def get_nonce(self, address: str) -> int:
+        """
+        Return the nonce of the account
+        """
+
+    def set_nonce(self, address: str, nonce: int):
+        """
+        Set the nonce of an account. Other account fields are not changed.
+        """
+
+    def get_code(self, address: str) -> bytes:
+        """
+        Return the runtime bytecode of the account.  Empty if the account has no code
+        """
+
+    def set_code(self, address: str, code: bytes):
+        """
+        Set (etch) the runtime bytecode of an account. The constructor is not run and
+        other account fields are not changed. Empty `code` removes the code.
+        """
+
+    def get_storage_at(self, address: str, slot: int) -> int:
+        """
+        Return the value of the storage `slot` of the account
+        """
+
+    def set_storage_at(self, address: str, slot: int, value: int):
+        """
+        Set the value of the storage `slot` of the account
+        """
+
+    def stub_precompile(self, address: str, output: bytes, gas_used: int = 0):
+        """
+        Register a precompile at `address` that always returns `output` and uses
+        `gas_used`.  It replaces any standard precompile or code at the address
+        """
+
+    def register_precompile(
+        self,
+        address: str,
+        callback: Callable[[bytes], Union[bytes, Tuple[bytes, int]]],
+        gas_used: int = 0,
+    ):
+        """
+        Register a Python function as a precompile at `address`, so contracts can call
+        out to Python.
+
+        - `callback`: called with the calldata. Returns the output, or a tuple of the
+           output and the gas used.  An exception reverts the call with the exception as the reason
+        - `gas_used`: optional. gas used by each call unless the callback returns it. Default: 0
+        """
+
+    def remove_precompile(self, address: str) -> bool:
+        """
+        Remove a precompile added with `stub_precompile` or `register_precompile`.
+        Returns False if there wasn't one
+        """
+
+    def transfer(
+        self,
+        caller: str,
+        to: str,
+        amount: int,
+        gas_limit: Optional[int] = None,
+        gas_price: Optional[int] = None,
+        max_fee_per_gas: Optional[int] = None,
+        max_priority_fee_per_gas: Optional[int] = None,
+    ) -> TxResult:
         """
         Transfer an 'amount' of Wei/Eth from 'caller' -> 'to'
 
         - `caller`: sender
         - `to`: recipient
         - `amount`: amount to transfer
+        - `gas_limit`: optional. gas limit of the transaction. Default: the block gas limit
+        - `gas_price`: optional. price per unit of gas for a legacy transaction
+        - `max_fee_per_gas`: optional. max fee per unit of gas (EIP-1559)
+        - `max_priority_fee_per_gas`: optional. max tip per unit of gas (EIP-1559)
+
+        If no gas pricing is set, the transaction is free.
+        """
+
+    def deploy(
+        self,
+        args: str,
+        caller: str,
+        value: int,
+        abi: PyAbi,
+        gas_limit: Optional[int] = None,
+        gas_price: Optional[int] = None,
+        max_fee_per_gas: Optional[int] = None,
+        max_priority_fee_per_gas: Optional[int] = None,
+    ) -> str:
+        """
+        Deploy a contract. See `Contract` for the recommended way to use this.
         """
 
-    def deploy(self, args: str, caller: str, value: int, abi: PyAbi) -> str:
+    def deploy_at(
+        self, address: str, args: str, caller: str, value: int, abi: PyAbi
+    ) -> str:
         """
-        Deploy a contract. See `Contract` for the recommended way to use this.
+        Deploy a contract at `address`. The constructor is run at `address` by `caller`,
+        then the runtime code it returns is placed at `address`.
+        See `Contract.deploy_at` for the recommended way to use this.
+        """
+
+    def deploy_create2(
+        self,
+        args: str,
+        caller: str,
+        salt: str,
+        value: int,
+        abi: PyAbi,
+        gas_limit: Optional[int] = None,
+        gas_price: Optional[int] = None,
+        max_fee_per_gas: Optional[int] = None,
+        max_priority_fee_per_gas: Optional[int] = None,
+    ) -> str:
+        """
+        Deploy a contract with CREATE2 through the deterministic deployment factory at
+        `0x4e59b44847b379578588920ca78fbf26c0b4956c`. `salt` is 32 bytes of hex.
+        See `Contract.deploy` for the recommended way to use this.
+        """
+
+    def compute_create_address(self, deployer: str, nonce: Optional[int] = None) -> str:
+        """
+        Return the address of a contract created with CREATE by `deployer`.
+
+        - `nonce`: optional. Default: the current nonce of `deployer`, i.e. the
+          address of its next deployment
+        """
+
+    def compute_create2_address(
+        self, salt: str, init_code: bytes, deployer: Optional[str] = None
+    ) -> str:
+        """
+        Return the address of a contract created with CREATE2 with `salt` (32 bytes of hex)
+        and `init_code` (the bytecode and encoded constructor arguments).
+
+        - `deployer`: optional. Default: the deterministic deployment factory used by
+          `deploy_create2`
+        """
+
+    def debug_trace_call(
+        self,
+        fn_name: str,
+        args: str,
+        caller: str,
+        to: str,
+        value: int,
+        abi: PyAbi,
+        enable_memory: bool = False,
+        disable_stack: bool = False,
+        disable_storage: bool = False,
+    ) -> Dict[str, Any]:
+        """
+        Trace each opcode executed by a call to a contract w/o changing state. See
+        `Function.debug_trace` for the recommended way to use this.
+
+        Returns a dict in the same format as the JSON of geth's `debug_traceTransaction`
+        default struct logger.  Only calls are traced: committed transactions, including
+        those of a forked chain, can't be replayed.  To compare with a node's trace of a
+        transaction, fork at the block before it and trace the same call
+        """
+
+    def estimate_gas(
+        self,
+        fn_name: str,
+        args: str,
+        caller: str,
+        to: str,
+        value: int,
+        abi: PyAbi,
+    ) -> int:
+        """
+        Estimate the gas limit needed for a call to a contract to succeed w/o changing state.
+        See `Function.estimate_gas` for the recommended way to use this.
+        """
+
+    def create_access_list(
+        self,
+        fn_name: str,
+        args: str,
+        caller: str,
+        to: str,
+        value: int,
+        abi: PyAbi,
+    ) -> Dict[str, Any]:
+        """
+        Generate an EIP-2930 access list for a call to a contract w/o changing state. See
+        `Function.create_access_list` for the recommended way to use this.
+
+        Returns a dict with:
+        - `access_list`: a list of dicts with an `address` and hex encoded `storage_keys`
+        - `gas_used`: gas used with the access list
+        - `gas_used_without_access_list`: gas used without the access list
+        - `error`: why the call failed, e.g. the revert message, or None.  Like
+          `eth_createAccessList`, the access list of a failed call has the addresses and
+          slots accessed until it failed
+        """
+
+    def transact_bundle(
+        self, txs: List[Dict[str, Any]], all_or_nothing: bool = True
+    ) -> List[Any]:
+        """
+        Execute a bundle of transactions in order. Each transaction sees the changes
+        made by the transactions before it. See `Function.build_transaction` to make
+        a transaction.
+
+        If `all_or_nothing` is True, the exception of the transaction that fails is raised
+        and no state is changed. Otherwise, failed transactions are skipped.
+
+        Returns a list with the `TxResult` of each transaction, or the exception of a
+        failed transaction.
+        """
+
+    def simulate_bundle(
+        self, txs: List[Dict[str, Any]], all_or_nothing: bool = True
+    ) -> List[Any]:
+        """
+        Same as `transact_bundle` but does NOT change state
         """
 
     def advance_block(self, interval: Optional[int] = 12):
@@ -85,6 +575,142 @@ class PyEvm:
         - `interval`: optional. block time interval in seconds. Default: 12
         """
 
+    def get_block_number(self) -> int:
+        """
+        Return `block.number` of the current block
+        """
+
+    def get_timestamp(self) -> int:
+        """
+        Return `block.timestamp` of the current block
+        """
+
+    def warp(self, timestamp: int):
+        """
+        Set `block.timestamp` of the current block. It may go back in time.  With
+        `interval` mining, warping `interval` seconds or more past the start of the
+        current block mines it first
+        """
+
+    def roll(self, block_number: int):
+        """
+        Set `block.number` of the current block. It may go back, like `warp`. No block
+        is mined: transactions already committed in the current block move to the new
+        number
+        """
+
+    def set_mining_mode(
+        self, mode: str, interval: Optional[int] = None, transactions: Optional[int] = None
+    ):
+        """
+        Set when blocks are mined:
+        - `manual`: only by `mine_block` or `advance_block`. The default
+        - `auto`: a block is mined for each transaction, or every `transactions` transactions.
+          `interval` is the time between blocks. Default: 12
+        - `batch`: a block is mined every `transactions` transactions. Same as `auto`
+          with `transactions`
+        - `interval`: a block is mined every `interval` seconds of block time. Time only
+          moves with `warp`: warping `interval` seconds or more past the start of the
+          current block mines it, and the next block starts at the new timestamp
+
+        Transactions sent with `transact`, `deploy`, etc... bypass the mempool and are
+        executed immediately in the current block
+        """
+
+    def get_mining_mode(self) -> Dict[str, Any]:
+        """
+        Return the mining mode as a dict of the arguments of `set_mining_mode`:
+        `mode` (`manual`, `auto`, `batch` or `interval`), `interval` and `transactions`
+        """
+
+    def set_tx_ordering(self, ordering: str):
+        """
+        Set the order pending transactions are mined in: `fifo` (default) or `priority_fee`
+        """
+
+    def send_transaction(
+        self,
+        fn_name: str,
+        args: str,
+        caller: str,
+        to: str,
+        value: int,
+        abi: PyAbi,
+        gas_limit: Optional[int] = None,
+        gas_price: Optional[int] = None,
+        max_fee_per_gas: Optional[int] = None,
+        max_priority_fee_per_gas: Optional[int] = None,
+    ) -> str:
+        """
+        Queue a transaction in the mempool, returning its hash. It's executed when the
+        next block is mined. See `Function.send` for the recommended way to use this.
+        Raises an exception if the transaction is mined right away and dropped because
+        it's invalid
+        """
+
+    def pending_transactions(self) -> List[str]:
+        """
+        Return the hashes of the transactions waiting to be mined
+        """
+
+    def mine_block(self, interval: Optional[int] = 12) -> Dict[str, Any]:
+        """
+        Execute the pending transactions in a block and start a new block.
+
+        - `interval`: optional. time in seconds until the next block. Default: 12
+
+        Returns the block header as a dict with the keys: `number`, `hash`, `parent_hash`,
+        `timestamp`, `gas_used`, `gas_limit`, `base_fee` and `transactions`, the hashes
+        of all the transactions in the block.  `dropped` lists the invalid transactions
+        that were dropped, e.g. with a gas price below the base fee, as dicts with the
+        keys `hash` and `error`
+        """
+
+    def get_transaction(self, hash: str) -> Optional[Dict[str, Any]]:
+        """
+        Return a committed transaction as a dict with the keys: `hash`, `from`, `to`,
+        `value`, `input`, `gas_limit`, `block_number` and `transaction_index`.
+        None if the hash is unknown
+        """
+
+    def get_receipt(self, hash: str) -> Optional[Dict[str, Any]]:
+        """
+        Return the receipt of a committed transaction as a dict with the keys:
+        `transaction_hash`, `block_number`, `transaction_index`, `status`, `gas_used`,
+        `cumulative_gas_used`, `effective_gas_price`, `contract_address`, `logs` and
+        `logs_bloom`. None if the hash is unknown
+        """
+
+    def get_block(self, number: int) -> Optional[Dict[str, Any]]:
+        """
+        Return a mined block in the same format as `mine_block`. None if the block
+        has not been mined
+        """
+
+    def get_logs(
+        self,
+        address: Optional[Union[str, List[str]]] = None,
+        topics: Optional[List[Optional[Union[str, List[str]]]]] = None,
+        from_block: Optional[int] = None,
+        to_block: Optional[int] = None,
+        abi: Optional[PyAbi] = None,
+    ) -> List[Dict[str, Any]]:
+        """
+        Return the logs emitted by committed transactions that match the filter, in the
+        order they were emitted.
+
+        - `address`: optional. the contract address, or a list of addresses, that emitted the log
+        - `topics`: optional. topics by position. `None` matches any topic, a list matches any of its values.
+           Addresses are padded to 32 bytes
+        - `from_block`: optional. the first block to include
+        - `to_block`: optional. the last block to include
+        - `abi`: optional. decode the logs as events of the contract
+
+        Each log is a dict with the keys: `address`, `topics`, `data`, `block_number`,
+        `transaction_hash`, `transaction_index`, and `log_index`.  With an `abi`, `event`
+        and `args` are the name and values of the event, or None if it's not in the ABI
+        """
+
 class PyAbi:
     """
     Load, parse, and encode Solidity ABI information
@@ -126,3 +126,33 @@ def contract_from_inline_abi(evm: PyEvm, abi: typing.List[str]) -> Contract:
 
     abi = PyAbi.from_human_readable(abi)
     return Contract(evm, abi)
+
+
+def format_gas_report(report: typing.Dict[str, typing.Any]) -> str:
+    """
+    Format the result of `PyEvm.get_gas_report` as a table, similar
+    to `forge test --gas-report`
+
+    - `report`: the gas report
+    Returns the table as a string
+    """
+    columns = ["min", "avg", "median", "max", "calls"]
+    rows = [["Function"] + columns]
+    for f in report["functions"]:
+        name = f["name"] or f["selector"] or "<receive>"
+        rows.append([f"{f['address']} {name}"] + [str(f[c]) for c in columns])
+
+    widths = [max(len(row[i]) for row in rows) for i in range(len(rows[0]))]
+    lines = [" | ".join(v.ljust(w) for v, w in zip(row, widths)) for row in rows]
+
+    lines.append("")
+    lines.append("Deployment | gas | size")
+    for d in report["deployments"]:
+        lines.append(f"{d['address']} | {d['gas']} | {d['size']}")
+
+    lines.append("")
+    lines.append("Opcodes | count | gas")
+    for category, used in report["opcodes"].items():
+        lines.append(f"{category} | {used['count']} | {used['gas']}")
+
+    return "\n".join(lines)
@@ -1,9 +1,10 @@
 //!
 //! Parse contract ABIs to encode, decode contract calls
 //!
-use alloy_dyn_abi::{DynSolEvent, DynSolType, DynSolValue, Specifier};
+use alloy_dyn_abi::{DynSolEvent, DynSolType, DynSolValue, JsonAbiExt, Specifier};
 use alloy_json_abi::{ContractObject, Function, JsonAbi, StateMutability};
 use alloy_primitives::{Bytes, Log, LogData};
+use alloy_sol_types::{Panic, Revert, SolError};
 use anyhow::{anyhow, bail, Result};
 use std::collections::BTreeMap;
 
@@ -59,6 +60,18 @@ fn convert_events(ev: &EventMap) -> Vec<EventLog> {
         .collect::<Vec<EventLog>>()
 }
 
+/// Decode revert data for Solidity's built-in `Error(string)` and `Panic(uint256)`.
+/// Returns the name of the error and the decoded arguments
+pub fn decode_builtin_error(data: &[u8]) -> Option<(String, Vec<DynSolValue>)> {
+    if let Ok(revert) = Revert::abi_decode(data, true) {
+        return Some(("Error".into(), vec![DynSolValue::String(revert.reason)]));
+    }
+    if let Ok(panic) = Panic::abi_decode(data, true) {
+        return Some(("Panic".into(), vec![DynSolValue::Uint(panic.code, 256)]));
+    }
+    None
+}
+
 impl ContractAbi {
     /// Parse the `abi` and `bytecode` from a compiled contract's json file.
     /// Note: `raw` is un-parsed json.
@@ -122,6 +135,28 @@ impl ContractAbi {
         self.abi.functions.contains_key(name)
     }
 
+    /// Return the function with the given 4 byte selector, if any
+    pub fn function_by_selector(&self, selector: &[u8]) -> Option<&Function> {
+        self.abi
+            .functions()
+            .find(|f| f.selector().as_slice() == selector)
+    }
+
+    /// Decode revert data using the custom `error`s in the ABI, falling back to the
+    /// built-in errors. Returns the name of the error and the decoded arguments
+    pub fn decode_error(&self, data: &[u8]) -> Option<(String, Vec<DynSolValue>)> {
+        let selector = data.get(..4)?;
+        self.abi
+            .errors()
+            .filter(|e| e.selector().as_slice() == selector)
+            .find_map(|e| {
+                e.abi_decode_input(&data[4..], true)
+                    .ok()
+                    .map(|args| (e.name.clone(), args))
+            })
+            .or_else(|| decode_builtin_error(data))
+    }
+
     /// Does the ABI have a fallback?
     pub fn has_fallback(&self) -> bool {
         self.abi.fallback.is_some()
@@ -213,7 +248,7 @@ impl ContractAbi {
         for f in funcs {
             let result = Self::extract(f, args);
             let is_payable = matches!(f.state_mutability, StateMutability::Payable);
-            if result.is_ok() {
+            if let Ok(values) = result {
                 // Get the return type decoder, if any...
                 let ty = match f.outputs.len() {
                     0 => None,
@@ -229,7 +264,7 @@ impl ContractAbi {
                 };
 
                 let selector = f.selector().to_vec();
-                let encoded_args = result.unwrap().abi_encode_params();
+                let encoded_args = values.abi_encode_params();
                 let all = [selector, encoded_args].concat();
 
                 return Ok((all, is_payable, ty));
@@ -248,7 +283,7 @@ impl ContractAbi {
 mod tests {
 
     use super::*;
-    use alloy_primitives::{b256, bytes, Address, LogData};
+    use alloy_primitives::{b256, bytes, Address, LogData, U256};
 
     #[test]
     fn check_constructor_encoding() {
@@ -297,7 +332,7 @@ mod tests {
         assert!(hw.encode_function("hello", "(1,2").is_err());
 
         let (_, is_payable, dtype) = hw
-            .encode_function("hello", &format!("(({}, {}, {}))", 10, addy.to_string(), 1))
+            .encode_function("hello", &format!("(({}, {}, {}))", 10, addy, 1))
             .unwrap();
 
         assert!(!is_payable);
@@ -315,12 +350,47 @@ mod tests {
         let addy = Address::with_last_byte(24);
 
         let (_, _, otype) = abi
-            .encode_function("one", &format!("({},({},{}))", addy.to_string(), 10, 11))
+            .encode_function("one", &format!("({},({},{}))", addy, 10, 11))
             .unwrap();
 
         assert_eq!(Some(DynSolType::Address), otype);
     }
 
+    #[test]
+    fn decoding_errors() {
+        let abi = ContractAbi::from_human_readable(vec![
+            "error InsufficientBalance(uint256 available, uint256 required)",
+        ]);
+
+        let error = &abi.abi.errors["InsufficientBalance"][0];
+        let data = error
+            .abi_encode_input(&[
+                DynSolValue::Uint(U256::from(10), 256),
+                DynSolValue::Uint(U256::from(20), 256),
+            ])
+            .unwrap();
+        let (name, args) = abi.decode_error(&data).unwrap();
+        assert_eq!("InsufficientBalance", name);
+        assert_eq!(
+            vec![
+                DynSolValue::Uint(U256::from(10), 256),
+                DynSolValue::Uint(U256::from(20), 256)
+            ],
+            args
+        );
+
+        let data = Revert::from("nope").abi_encode();
+        assert_eq!(
+            Some(("Error".into(), vec![DynSolValue::String("nope".into())])),
+            abi.decode_error(&data)
+        );
+        let data = Panic::from(0x11).abi_encode();
+        assert_eq!("Panic", decode_builtin_error(&data).unwrap().0);
+
+        assert!(abi.decode_error(&[1, 2, 3]).is_none());
+        assert!(abi.decode_error(&[1, 2, 3, 4]).is_none());
+    }
+
     #[test]
     fn test_flatten_event_structure() {
         // mint signature: 0x0f6798a560793a54c3bcfe86a93cde1e73087d944c0ea20544137d4121396885
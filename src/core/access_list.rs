@@ -0,0 +1,105 @@
+//!
+//! Generate EIP-2930 access lists.  Same as geth's `eth_createAccessList`
+//!
+use alloy_primitives::{Address, U256};
+use revm::{
+    interpreter::{opcode, CallInputs, CallOutcome, Interpreter},
+    primitives::SpecId,
+    Database, EvmContext, Inspector,
+};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::core::errors::ExecutionError;
+
+/// Addresses and storage slots accessed by a transaction
+pub type AccessList = Vec<(Address, Vec<U256>)>;
+
+/// The result of `BaseEvm::create_access_list`
+#[derive(Debug)]
+pub struct AccessListResult {
+    /// the addresses and slots accessed.  If the transaction failed, the ones
+    /// accessed until it failed
+    pub access_list: AccessList,
+    /// gas used by the transaction with the access list
+    pub gas_used: u64,
+    /// gas used by the transaction without the access list
+    pub gas_used_without_access_list: u64,
+    /// why the transaction failed, e.g. it reverted
+    pub error: Option<ExecutionError>,
+}
+
+/// An `Inspector` that records the addresses and storage slots accessed.
+///
+/// The sender, recipient, precompiles and, from Shanghai, `block.coinbase` (EIP-3651) are
+/// excluded from the addresses, as they are always warm.  Storage slots of the recipient
+/// are included.
+#[derive(Debug, Default)]
+pub struct AccessListTracer {
+    excluded: HashSet<Address>,
+    accessed: BTreeMap<Address, BTreeSet<U256>>,
+}
+
+impl AccessListTracer {
+    /// Return the access list
+    pub fn into_access_list(self) -> AccessList {
+        self.accessed
+            .into_iter()
+            .map(|(address, slots)| (address, slots.into_iter().collect()))
+            .collect()
+    }
+
+    fn add_address(&mut self, address: Address) {
+        if !self.excluded.contains(&address) {
+            self.accessed.entry(address).or_default();
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for AccessListTracer {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let stack = interp.stack.data();
+        let address_at = |i: usize| Address::from_word(stack[stack.len() - i].into());
+        match interp.current_opcode() {
+            opcode::SLOAD | opcode::SSTORE if !stack.is_empty() => {
+                let slot = stack[stack.len() - 1];
+                self.accessed
+                    .entry(interp.contract.address)
+                    .or_default()
+                    .insert(slot);
+            }
+            opcode::EXTCODECOPY
+            | opcode::EXTCODEHASH
+            | opcode::EXTCODESIZE
+            | opcode::BALANCE
+            | opcode::SELFDESTRUCT
+                if !stack.is_empty() =>
+            {
+                self.add_address(address_at(1));
+            }
+            opcode::CALL | opcode::CALLCODE | opcode::DELEGATECALL | opcode::STATICCALL
+                if stack.len() >= 2 =>
+            {
+                self.add_address(address_at(2));
+            }
+            _ => {}
+        }
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        // the transaction itself
+        if context.journaled_state.depth() == 0 {
+            self.excluded.insert(inputs.context.caller);
+            self.excluded.insert(inputs.context.address);
+            self.excluded
+                .extend(context.precompiles.addresses().copied());
+            if context.spec_id().is_enabled_in(SpecId::SHANGHAI) {
+                self.excluded.insert(context.env.block.coinbase);
+            }
+        }
+        None
+    }
+}
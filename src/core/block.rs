@@ -0,0 +1,154 @@
+//!
+//! Block and chain settings used to build the EVM environment
+//!
+use alloy_primitives::{keccak256, Address, B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// default gas limit for a block (same as mainnet)
+pub const DEFAULT_BLOCK_GAS_LIMIT: u64 = 30_000_000;
+
+/// Values available to contracts through `block.*` and `block.chainid`.
+///
+/// `block.number` and `block.timestamp` are managed separately by the
+/// storage backend.  See `BaseEvm::update_block`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockConfig {
+    /// `block.chainid`
+    pub chain_id: u64,
+    /// `block.coinbase`: the beneficiary of transaction fees
+    pub coinbase: Address,
+    /// `block.gaslimit`
+    pub gas_limit: u64,
+    /// `block.basefee`
+    pub basefee: U256,
+    /// `block.prevrandao`
+    pub prevrandao: B256,
+    /// `block.difficulty`. Only used for hardforks before the merge
+    pub difficulty: U256,
+}
+
+impl Default for BlockConfig {
+    fn default() -> Self {
+        Self {
+            chain_id: 1,
+            coinbase: Address::ZERO,
+            gas_limit: DEFAULT_BLOCK_GAS_LIMIT,
+            basefee: U256::ZERO,
+            prevrandao: B256::ZERO,
+            difficulty: U256::ZERO,
+        }
+    }
+}
+
+/// Settings for the EIP-1559 base fee market.  When enabled, `block.basefee`
+/// is adjusted each block based on how much gas the previous block used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeeMarket {
+    /// the gas target for a block is `gas_limit / elasticity_multiplier`.
+    pub elasticity_multiplier: u64,
+    /// bounds how much the base fee can change between blocks. The max
+    /// change is `basefee / base_fee_max_change_denominator`.
+    pub base_fee_max_change_denominator: u64,
+}
+
+impl Default for FeeMarket {
+    /// Mainnet values
+    fn default() -> Self {
+        Self {
+            elasticity_multiplier: 2,
+            base_fee_max_change_denominator: 8,
+        }
+    }
+}
+
+impl FeeMarket {
+    /// Return the gas target for a block with the given `gas_limit`
+    pub fn gas_target(&self, gas_limit: u64) -> u64 {
+        gas_limit / self.elasticity_multiplier.max(1)
+    }
+
+    /// Calculate the base fee of the next block using the EIP-1559 formula
+    pub fn next_base_fee(&self, basefee: U256, gas_used: u64, gas_limit: u64) -> U256 {
+        let target = self.gas_target(gas_limit);
+        if target == 0 || gas_used == target {
+            return basefee;
+        }
+        let denominator = U256::from(self.base_fee_max_change_denominator.max(1));
+
+        if gas_used > target {
+            let delta = basefee * U256::from(gas_used - target) / U256::from(target) / denominator;
+            basefee + delta.max(U256::from(1))
+        } else {
+            let delta = basefee * U256::from(target - gas_used) / U256::from(target) / denominator;
+            basefee.saturating_sub(delta)
+        }
+    }
+}
+
+/// The header of a mined block.  See `BaseEvm::mine_block`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub number: u64,
+    pub timestamp: u64,
+    /// gas used by the transactions in the block
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    pub base_fee: U256,
+    /// hash of the previous block. Zero for the first block mined
+    pub parent_hash: B256,
+}
+
+impl BlockHeader {
+    /// A deterministic hash of the header.  This is not the RLP hash used by Ethereum.
+    pub fn hash(&self) -> B256 {
+        keccak256(
+            [
+                self.parent_hash.as_slice(),
+                &self.number.to_be_bytes(),
+                &self.timestamp.to_be_bytes(),
+                &self.gas_used.to_be_bytes(),
+                &self.gas_limit.to_be_bytes(),
+                &self.base_fee.to_be_bytes::<32>(),
+            ]
+            .concat(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_base_fee() {
+        let market = FeeMarket::default();
+        let limit = DEFAULT_BLOCK_GAS_LIMIT;
+        let basefee = U256::from(1_000_000_000u64);
+
+        assert_eq!(basefee, market.next_base_fee(basefee, limit / 2, limit));
+        // full block: +12.5%
+        assert_eq!(
+            U256::from(1_125_000_000u64),
+            market.next_base_fee(basefee, limit, limit)
+        );
+        // empty block: -12.5%
+        assert_eq!(
+            U256::from(875_000_000u64),
+            market.next_base_fee(basefee, 0, limit)
+        );
+        // always increases by at least 1 above the target
+        assert_eq!(
+            U256::from(1),
+            market.next_base_fee(U256::ZERO, limit, limit)
+        );
+
+        let custom = FeeMarket {
+            elasticity_multiplier: 4,
+            base_fee_max_change_denominator: 4,
+        };
+        assert_eq!(
+            U256::from(1_250_000_000u64),
+            custom.next_base_fee(basefee, limit / 2, limit)
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//!
+//! Record the tree of calls made during a transaction
+//!
+use alloy_primitives::{Address, Bytes, U256};
+use revm::{
+    interpreter::{CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome},
+    primitives::CreateScheme,
+    Database, EvmContext, Inspector,
+};
+
+/// The type of call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+impl CallKind {
+    /// The name of the opcode. Same as geth's `callTracer`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CallKind::Call => "CALL",
+            CallKind::CallCode => "CALLCODE",
+            CallKind::DelegateCall => "DELEGATECALL",
+            CallKind::StaticCall => "STATICCALL",
+            CallKind::Create => "CREATE",
+            CallKind::Create2 => "CREATE2",
+        }
+    }
+}
+
+impl From<CallScheme> for CallKind {
+    fn from(scheme: CallScheme) -> Self {
+        match scheme {
+            CallScheme::Call => CallKind::Call,
+            CallScheme::CallCode => CallKind::CallCode,
+            CallScheme::DelegateCall => CallKind::DelegateCall,
+            CallScheme::StaticCall => CallKind::StaticCall,
+        }
+    }
+}
+
+/// A single call in the call tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallTrace {
+    /// the type of call
+    pub kind: CallKind,
+    /// the address making the call
+    pub from: Address,
+    /// the address called, or the address of the new contract
+    pub to: Address,
+    /// the amount of wei sent with the call
+    pub value: U256,
+    /// calldata, or init code for a create
+    pub input: Bytes,
+    /// returned data, or revert data if the call failed
+    pub output: Bytes,
+    /// gas available to the call
+    pub gas_limit: u64,
+    /// gas used by the call, including any nested calls
+    pub gas_used: u64,
+    /// did the call succeed?
+    pub success: bool,
+    /// the reason the call failed, e.g. `Revert` or `OutOfGas`
+    pub error: Option<String>,
+    /// calls made by this call, in order
+    pub calls: Vec<CallTrace>,
+}
+
+/// An `Inspector` that records the nested CALL/DELEGATECALL/STATICCALL/CREATE
+/// tree of a transaction.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    // calls that have started but not finished
+    stack: Vec<CallTrace>,
+    // the top-level call, once finished
+    root: Option<CallTrace>,
+}
+
+impl CallTracer {
+    /// Return the call tree. None if nothing was executed.
+    pub fn into_trace(self) -> Option<CallTrace> {
+        self.root
+    }
+
+    fn start(&mut self, trace: CallTrace) {
+        self.stack.push(trace);
+    }
+
+    fn finish(
+        &mut self,
+        outcome_to: Option<Address>,
+        output: &Bytes,
+        gas_used: u64,
+        ok: bool,
+        error: Option<String>,
+    ) {
+        let Some(mut trace) = self.stack.pop() else {
+            return;
+        };
+        if let Some(to) = outcome_to {
+            trace.to = to;
+        }
+        trace.output = output.clone();
+        trace.gas_used = gas_used;
+        trace.success = ok;
+        trace.error = error;
+
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(trace),
+            None => self.root = Some(trace),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let kind = CallKind::from(inputs.context.scheme);
+        let (from, to) = match kind {
+            // the code of `to` runs in the context of the caller
+            CallKind::DelegateCall | CallKind::CallCode => {
+                (inputs.context.address, inputs.context.code_address)
+            }
+            _ => (inputs.context.caller, inputs.context.address),
+        };
+        self.start(CallTrace {
+            kind,
+            from,
+            to,
+            value: inputs.transfer.value,
+            input: inputs.input.clone(),
+            output: Bytes::new(),
+            gas_limit: inputs.gas_limit,
+            gas_used: 0,
+            success: false,
+            error: None,
+            calls: vec![],
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        let result = &outcome.result;
+        let ok = result.result.is_ok();
+        let error = (!ok).then(|| format!("{:?}", result.result));
+        self.finish(None, &result.output, result.gas.spent(), ok, error);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        let kind = match inputs.scheme {
+            CreateScheme::Create => CallKind::Create,
+            CreateScheme::Create2 { .. } => CallKind::Create2,
+        };
+        self.start(CallTrace {
+            kind,
+            from: inputs.caller,
+            to: Address::ZERO,
+            value: inputs.value,
+            input: inputs.init_code.clone(),
+            output: Bytes::new(),
+            gas_limit: inputs.gas_limit,
+            gas_used: 0,
+            success: false,
+            error: None,
+            calls: vec![],
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        let result = &outcome.result;
+        let ok = result.result.is_ok();
+        let error = (!ok).then(|| format!("{:?}", result.result));
+        self.finish(
+            outcome.address,
+            &result.output,
+            result.gas.spent(),
+            ok,
+            error,
+        );
+        outcome
+    }
+}
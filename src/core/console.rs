@@ -0,0 +1,199 @@
+//!
+//! Capture the messages logged with Hardhat's `console.sol` during a transaction.
+//!
+//! `console.log` calls a contract-less address that does nothing, so the messages
+//! are decoded from the calldata of calls to that address.
+//!
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::{address, hex, keccak256, Address};
+use revm::{
+    interpreter::{CallInputs, CallOutcome},
+    Database, EvmContext, Inspector,
+};
+use std::{collections::HashMap, sync::OnceLock};
+
+/// The address called by `console.log`
+pub const CONSOLE_ADDRESS: Address = address!("000000000000000000636F6e736F6c652e6c6f67");
+
+/// Records the messages logged with `console.log`, in order
+#[derive(Debug, Default)]
+pub struct ConsoleLogger {
+    pub messages: Vec<String>,
+}
+
+impl<DB: Database> Inspector<DB> for ConsoleLogger {
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        if inputs.contract == CONSOLE_ADDRESS {
+            if let Some(message) = decode_message(&inputs.input) {
+                self.messages.push(message);
+            }
+        }
+        None
+    }
+}
+
+/// Decode the calldata of a call to `console.log`.  None if it's not a known signature
+pub fn decode_message(input: &[u8]) -> Option<String> {
+    let selector: [u8; 4] = input.get(..4)?.try_into().ok()?;
+    let types = signatures().get(&selector)?;
+    let values = DynSolType::Tuple(types.clone())
+        .abi_decode_params(&input[4..])
+        .ok()?;
+    Some(format_message(values.as_tuple()?))
+}
+
+// Types of the parameters of each `console` function, by selector
+fn signatures() -> &'static HashMap<[u8; 4], Vec<DynSolType>> {
+    static SIGNATURES: OnceLock<HashMap<[u8; 4], Vec<DynSolType>>> = OnceLock::new();
+    SIGNATURES.get_or_init(|| {
+        let mut functions: Vec<(String, Vec<&str>)> = vec![
+            ("log".into(), vec![]),
+            ("logInt".into(), vec!["int256"]),
+            ("logUint".into(), vec!["uint256"]),
+            ("logString".into(), vec!["string"]),
+            ("logBool".into(), vec!["bool"]),
+            ("logAddress".into(), vec!["address"]),
+            ("logBytes".into(), vec!["bytes"]),
+            ("log".into(), vec!["int256"]),
+        ];
+        let bytes = (1..=32).map(|n| format!("bytes{n}")).collect::<Vec<_>>();
+        for (n, ty) in (1..=32).zip(&bytes) {
+            functions.push((format!("logBytes{n}"), vec![ty]));
+        }
+
+        // `log` with 1 to 4 parameters of these types, in any order
+        let types = ["uint256", "string", "bool", "address"];
+        let mut params: Vec<Vec<&str>> = vec![vec![]];
+        for _ in 0..4 {
+            params = params
+                .iter()
+                .flat_map(|p| {
+                    types.iter().map(move |ty| {
+                        let mut p = p.clone();
+                        p.push(ty);
+                        p
+                    })
+                })
+                .collect();
+            functions.extend(params.iter().map(|p| ("log".into(), p.clone())));
+        }
+
+        let mut signatures = HashMap::new();
+        for (name, params) in functions {
+            let signature = format!("{}({})", name, params.join(","));
+            let types = params
+                .iter()
+                .map(|ty| DynSolType::parse(ty).expect("console: invalid type"))
+                .collect::<Vec<_>>();
+            // older versions of console.sol use `uint` and `int` in the signatures
+            for signature in [signature.replace("int256", "int"), signature] {
+                let selector = keccak256(signature.as_bytes());
+                signatures.insert(selector[..4].try_into().unwrap(), types.clone());
+            }
+        }
+        signatures
+    })
+}
+
+// Format the values like Hardhat (and Node's `util.format`).  `%s`, `%d`, `%i`
+// and `%o` in a first string value are replaced by the values that follow it,
+// and any values left are appended separated by spaces.
+fn format_message(values: &[DynSolValue]) -> String {
+    let mut rest = values.iter();
+    let mut parts = Vec::new();
+    if let Some(DynSolValue::String(template)) = values.first() {
+        rest.next();
+        let mut message = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                message.push(c);
+                continue;
+            }
+            match chars.peek() {
+                Some('%') => {
+                    chars.next();
+                    message.push('%');
+                }
+                Some('s' | 'd' | 'i' | 'o') => match rest.next() {
+                    Some(value) => {
+                        chars.next();
+                        message.push_str(&format_value(value));
+                    }
+                    None => message.push('%'),
+                },
+                _ => message.push('%'),
+            }
+        }
+        parts.push(message);
+    }
+    parts.extend(rest.map(format_value));
+    parts.join(" ")
+}
+
+fn format_value(value: &DynSolValue) -> String {
+    match value {
+        DynSolValue::String(s) => s.clone(),
+        DynSolValue::Bool(b) => b.to_string(),
+        DynSolValue::Uint(n, _) => n.to_string(),
+        DynSolValue::Int(n, _) => n.to_string(),
+        DynSolValue::Address(address) => address.to_checksum(None),
+        DynSolValue::Bytes(bytes) => hex::encode_prefixed(bytes),
+        DynSolValue::FixedBytes(word, size) => hex::encode_prefixed(&word[..*size]),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{B256, U256};
+
+    fn calldata(signature: &str, values: Vec<DynSolValue>) -> Vec<u8> {
+        let mut data = keccak256(signature.as_bytes())[..4].to_vec();
+        data.extend(DynSolValue::Tuple(values).abi_encode_params());
+        data
+    }
+
+    #[test]
+    fn decode() {
+        let bob = Address::repeat_byte(0xab);
+        let data = calldata(
+            "log(string,uint256,address)",
+            vec![
+                DynSolValue::String("%s%% sent to".into()),
+                DynSolValue::Uint(U256::from(10), 256),
+                DynSolValue::Address(bob),
+            ],
+        );
+        assert_eq!(
+            Some(format!("10% sent to {}", bob.to_checksum(None))),
+            decode_message(&data)
+        );
+
+        // the legacy signature
+        let data = calldata(
+            "log(uint,bool)",
+            vec![
+                DynSolValue::Uint(U256::from(1), 256),
+                DynSolValue::Bool(true),
+            ],
+        );
+        assert_eq!(Some("1 true".into()), decode_message(&data));
+
+        let data = calldata(
+            "logBytes2(bytes2)",
+            vec![DynSolValue::FixedBytes(B256::with_last_byte(0), 2)],
+        );
+        assert_eq!(Some("0x0000".into()), decode_message(&data));
+
+        // a missing value leaves the specifier
+        let data = calldata("log(string)", vec![DynSolValue::String("%d%".into())]);
+        assert_eq!(Some("%d%".into()), decode_message(&data));
+        assert_eq!(None, decode_message(&[1, 2, 3, 4]));
+    }
+}
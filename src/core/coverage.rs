@@ -0,0 +1,253 @@
+//!
+//! Code coverage.  Record the program counters executed and the branches taken
+//! by each bytecode, and map them back to the lines of the Solidity sources with
+//! solc source maps to produce an LCOV report.
+//!
+use alloy_primitives::{keccak256, Bytes, B256};
+use anyhow::{anyhow, Result};
+use revm::{
+    interpreter::{opcode, Interpreter},
+    Database, EvmContext, Inspector,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// What was executed in a bytecode
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BytecodeHits {
+    /// number of times each program counter was executed
+    pub pcs: BTreeMap<usize, u64>,
+    /// number of times each JUMPI, by program counter, jumped and didn't jump
+    pub branches: BTreeMap<usize, [u64; 2]>,
+}
+
+/// Coverage of each bytecode, by the hash of the bytecode
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoverageMap {
+    pub bytecodes: HashMap<B256, BytecodeHits>,
+}
+
+impl CoverageMap {
+    /// Add the coverage of `other`
+    pub fn merge(&mut self, other: CoverageMap) {
+        for (hash, hits) in other.bytecodes {
+            let total = self.bytecodes.entry(hash).or_default();
+            for (pc, count) in hits.pcs {
+                *total.pcs.entry(pc).or_default() += count;
+            }
+            for (pc, [jumped, not_jumped]) in hits.branches {
+                let branch = total.branches.entry(pc).or_default();
+                branch[0] += jumped;
+                branch[1] += not_jumped;
+            }
+        }
+    }
+
+    /// Create an LCOV report of the lines and branches of the `contracts` that were
+    /// executed.  Each source file is reported once, even if it's used by many contracts.
+    pub fn lcov(&self, contracts: &[ContractSources]) -> Result<String> {
+        let mut files: BTreeMap<String, FileCoverage> = BTreeMap::new();
+        for contract in contracts {
+            let hits = self.bytecodes.get(&keccak256(&contract.bytecode));
+            let lines = contract
+                .sources
+                .iter()
+                .map(|(index, (_, content))| (*index, line_starts(content)))
+                .collect::<HashMap<_, _>>();
+
+            let pcs = instruction_pcs(&contract.bytecode);
+            let source_map = parse_source_map(&contract.source_map)?;
+            for (pc, element) in pcs.iter().zip(source_map) {
+                let Some((id, (path, _))) = element
+                    .file
+                    .and_then(|id| contract.sources.get(&id).map(|source| (id, source)))
+                else {
+                    continue;
+                };
+                let line = line_number(&lines[&id], element.offset);
+                let file = files.entry(path.clone()).or_default();
+
+                let count = hits.and_then(|hits| hits.pcs.get(pc)).copied();
+                let line_hits = file.lines.entry(line).or_default();
+                *line_hits = (*line_hits).max(count.unwrap_or_default());
+
+                if contract.bytecode[*pc] == opcode::JUMPI {
+                    let taken = hits.and_then(|hits| hits.branches.get(pc)).copied();
+                    file.branches.insert((line, *pc), count.and(taken));
+                }
+            }
+        }
+
+        let mut report = String::new();
+        for (path, file) in files {
+            report.push_str(&format!("TN:\nSF:{}\n", path));
+            for (line, count) in &file.lines {
+                report.push_str(&format!("DA:{},{}\n", line, count));
+            }
+            report.push_str(&format!(
+                "LF:{}\nLH:{}\n",
+                file.lines.len(),
+                file.lines.values().filter(|count| **count > 0).count()
+            ));
+
+            let mut found = 0;
+            let mut hit = 0;
+            for ((line, pc), taken) in &file.branches {
+                for branch in 0..2 {
+                    found += 1;
+                    let count = match taken {
+                        Some(taken) => {
+                            hit += usize::from(taken[branch] > 0);
+                            taken[branch].to_string()
+                        }
+                        // the JUMPI was never reached
+                        None => "-".to_string(),
+                    };
+                    report.push_str(&format!("BRDA:{},{},{},{}\n", line, pc, branch, count));
+                }
+            }
+            report.push_str(&format!("BRF:{}\nBRH:{}\nend_of_record\n", found, hit));
+        }
+        Ok(report)
+    }
+}
+
+/// A deployed contract and the sources it was compiled from
+#[derive(Clone, Debug, Default)]
+pub struct ContractSources {
+    /// the runtime bytecode, as deployed
+    pub bytecode: Bytes,
+    /// the source map of the runtime bytecode from solc: `evm.deployedBytecode.sourceMap`
+    pub source_map: String,
+    /// the path and content of each source file, by its id in the solc output
+    pub sources: HashMap<u32, (String, String)>,
+}
+
+#[derive(Debug, Default)]
+struct FileCoverage {
+    // hits by line number
+    lines: BTreeMap<usize, u64>,
+    // hits of the 2 branches of a JUMPI, by line number and program counter.
+    // None if the JUMPI wasn't reached
+    branches: BTreeMap<(usize, usize), Option<[u64; 2]>>,
+}
+
+/// Record coverage during a transaction.  Only runtime code is covered: constructors
+/// run as init code, which doesn't have a code hash
+#[derive(Debug, Default)]
+pub struct CoverageCollector {
+    pub map: CoverageMap,
+}
+
+impl<DB: Database> Inspector<DB> for CoverageCollector {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        // init code of a CREATE
+        if interp.contract.hash == B256::ZERO {
+            return;
+        }
+        let pc = interp.program_counter();
+        let hits = self.map.bytecodes.entry(interp.contract.hash).or_default();
+        *hits.pcs.entry(pc).or_default() += 1;
+        if interp.current_opcode() == opcode::JUMPI {
+            // the condition is below the destination
+            if let Ok(condition) = interp.stack.peek(1) {
+                hits.branches.entry(pc).or_default()[usize::from(condition.is_zero())] += 1;
+            }
+        }
+    }
+}
+
+// An entry of a source map
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct SourceElement {
+    offset: usize,
+    // None for code that isn't from a source file
+    file: Option<u32>,
+}
+
+// Parse a compressed solc source map: `s:l:f:j:m;...`, where each empty field is the
+// same as in the previous entry.  Only the offset and file are used.
+fn parse_source_map(source_map: &str) -> Result<Vec<SourceElement>> {
+    let mut element = SourceElement::default();
+    let mut elements = Vec::new();
+    for entry in source_map.split(';') {
+        let fields = entry.split(':').collect::<Vec<_>>();
+        if let Some(offset) = fields.first().filter(|s| !s.is_empty()) {
+            element.offset = offset
+                .parse()
+                .map_err(|_| anyhow!("invalid offset in source map: {}", entry))?;
+        }
+        if let Some(file) = fields.get(2).filter(|s| !s.is_empty()) {
+            let file: i64 = file
+                .parse()
+                .map_err(|_| anyhow!("invalid file in source map: {}", entry))?;
+            element.file = u32::try_from(file).ok();
+        }
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+// The program counter of each instruction, skipping the data of PUSH opcodes
+fn instruction_pcs(bytecode: &[u8]) -> Vec<usize> {
+    let mut pcs = Vec::new();
+    let mut pc = 0;
+    while pc < bytecode.len() {
+        pcs.push(pc);
+        let op = bytecode[pc];
+        pc += 1;
+        if (opcode::PUSH1..=opcode::PUSH32).contains(&op) {
+            pc += (op - opcode::PUSH1 + 1) as usize;
+        }
+    }
+    pcs
+}
+
+// Byte offset of the start of each line
+fn line_starts(content: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+// 1-based line number of the byte `offset`
+fn line_number(starts: &[usize], offset: usize) -> usize {
+    starts.partition_point(|start| *start <= offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_map() {
+        assert_eq!(
+            vec![
+                SourceElement {
+                    offset: 1,
+                    file: Some(0)
+                },
+                SourceElement {
+                    offset: 5,
+                    file: Some(0)
+                },
+                SourceElement {
+                    offset: 5,
+                    file: None
+                },
+            ],
+            parse_source_map("1:2:0:-;5;::-1:i").unwrap()
+        );
+        // PUSH2 0x0102 ADD PUSH1
+        assert_eq!(vec![0, 3, 4], instruction_pcs(&[0x61, 1, 2, 0x01, 0x60]));
+
+        let starts = line_starts("ab\ncd\n");
+        assert_eq!(
+            (1, 1, 2),
+            (
+                line_number(&starts, 0),
+                line_number(&starts, 2),
+                line_number(&starts, 3)
+            )
+        );
+    }
+}
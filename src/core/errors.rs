@@ -1,9 +1,10 @@
 //!
-//! Database errors
+//! Database and execution errors
 //!
-use alloy_primitives::{Address, U256};
-use revm::primitives::EVMError;
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_sol_types::decode_revert_reason;
 use revm::primitives::B256;
+use revm::primitives::{EVMError, HaltReason};
 use thiserror::Error;
 
 use std::convert::Infallible;
@@ -39,3 +40,54 @@ impl From<Infallible> for DatabaseError {
         match value {}
     }
 }
+
+/// Why a transaction failed.  Returned (wrapped in an `anyhow::Error`) by the
+/// `BaseEvm` methods that execute a transaction.  Use `downcast_ref` to access it.
+#[derive(Error, Debug)]
+pub enum ExecutionError {
+    /// The transaction reverted. `data` is the raw revert data, e.g. an ABI encoded
+    /// `Error(string)` or custom error.  See `ContractAbi::decode_error`
+    #[error("{}", revert_message(.data, .gas_used))]
+    Revert { data: Bytes, gas_used: u64 },
+    /// The transaction halted, e.g. an invalid opcode
+    #[error("Halted: {reason:?}. Gas used: {gas_used}")]
+    Halt { reason: HaltReason, gas_used: u64 },
+    /// The transaction ran out of gas. `gas_limit` is the gas limit of the transaction.
+    /// `gas_used` is 0 if the gas limit doesn't cover the intrinsic gas
+    #[error("Out of gas with a gas limit of {gas_limit}. Gas used: {gas_used}")]
+    OutOfGas { gas_limit: u64, gas_used: u64 },
+    /// The gas limit of the transaction is more than the gas left in the block
+    #[error("gas limit of {gas_limit} exceeds the gas left in the block: {available}")]
+    GasLimitExceedsBlock { gas_limit: u64, available: u64 },
+    /// The database failed while executing the transaction
+    #[error("backend failed while executing transaction: {0}")]
+    Database(#[from] DatabaseError),
+}
+
+impl ExecutionError {
+    /// Gas used by a transaction that was executed and failed.  0 if the
+    /// transaction could not be executed
+    pub fn gas_used(&self) -> u64 {
+        match self {
+            ExecutionError::Revert { gas_used, .. }
+            | ExecutionError::Halt { gas_used, .. }
+            | ExecutionError::OutOfGas { gas_used, .. } => *gas_used,
+            _ => 0,
+        }
+    }
+}
+
+/// Context added to the error of the transaction that failed an all-or-nothing
+/// bundle. See `BaseEvm::transact_bundle`
+#[derive(Error, Debug)]
+#[error("transaction {index} of the bundle failed")]
+pub struct BundleError {
+    pub index: usize,
+}
+
+fn revert_message(data: &Bytes, gas_used: &u64) -> String {
+    match decode_revert_reason(data) {
+        Some(reason) => format!("Reverted: {:?}. Gas used: {:?}", reason, gas_used),
+        _ => format!("Reverted with no reason. Gas used: {:?}", gas_used),
+    }
+}
@@ -5,26 +5,142 @@
 //! is a simplfied version of [Foundry's Executor](https://github.com/foundry-rs/foundry)
 //!
 
-use alloy_primitives::{Address, Bytes, U256};
-use alloy_sol_types::decode_revert_reason;
+use alloy_primitives::{address, hex, Address, Bytes, B256, U256};
 use anyhow::{anyhow, bail, Result};
 use revm::{
     db::{DatabaseCommit, DatabaseRef},
+    interpreter::gas::CALL_STIPEND,
     primitives::{
-        Account, AccountInfo, BlockEnv, Env, EnvWithHandlerCfg, ExecutionResult, HashMap as Map,
-        Log, Output, ResultAndState, TransactTo, TxEnv,
+        Account, AccountInfo, BlockEnv, Bytecode, Env, EnvWithHandlerCfg, ExecutionResult,
+        HaltReason, HashMap as Map, Log, Output, ResultAndState, SpecId, TransactTo, TxEnv,
+        KECCAK_EMPTY,
     },
 };
+use std::{collections::BTreeMap, sync::Arc};
 
-use crate::{core::snapshot::SnapShot, core::storage::CreateFork, core::storage::StorageBackend};
+use crate::core::{
+    access_list::{AccessList, AccessListResult, AccessListTracer},
+    block::{BlockConfig, BlockHeader, FeeMarket},
+    call_tracer::{CallTrace, CallTracer},
+    console::ConsoleLogger,
+    coverage::{ContractSources, CoverageCollector, CoverageMap},
+    errors::{BundleError, ExecutionError},
+    gas_profiler::{GasProfiler, GasReport},
+    hardfork::{mainnet_spec_id, parse_spec_id, spec_id_name},
+    history::{Block, History, HistoryMark, Receipt, Transaction},
+    inspector::InspectorStack,
+    logs::{IndexedLog, LogFilter},
+    mempool::{DroppedTx, Mempool, MiningMode, TxOrdering},
+    precompile::CustomPrecompile,
+    snapshot::SnapShot,
+    storage::{CreateFork, StorageBackend},
+    struct_logger::{StructLogTrace, StructLogger, StructLoggerConfig},
+};
+
+/// gas used by a plain transfer: the least a transaction can use
+const MIN_TRANSACTION_GAS: u64 = 21_000;
+
+/// The deterministic deployment proxy used to deploy contracts with CREATE2.  It's
+/// at the same address on most chains, and is installed when using the in-memory
+/// database.  See `BaseEvm::deploy_create2`
+pub const CREATE2_FACTORY: Address = address!("4e59b44847b379578588920ca78fbf26c0b4956c");
+
+/// Runtime code of `CREATE2_FACTORY`.  Calldata is the salt followed by the init code
+const CREATE2_FACTORY_CODE: &[u8] = &hex!("7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffe03601600081602082378035828234f58015156039578182fd5b8082525050506014600cf3");
 
 /// type alias for a `revm` hashmap of `Address` => `Account`
 type StateChangeSet = Map<Address, Account>;
 
+/// Optional settings for a transaction.  The default is a transaction that
+/// doesn't pay for gas.
+///
+/// For a legacy transaction set `gas_price`.  For an EIP-1559 transaction set
+/// `max_fee_per_gas` and/or `max_priority_fee_per_gas`.  If only the priority fee
+/// is set, the max fee is `block.basefee + max_priority_fee_per_gas`.
+#[derive(Clone, Debug, Default)]
+pub struct TxOptions {
+    /// price per unit of gas for a legacy transaction
+    pub gas_price: Option<U256>,
+    /// max total fee per unit of gas the caller is willing to pay (EIP-1559)
+    pub max_fee_per_gas: Option<U256>,
+    /// max tip per unit of gas paid to the `block.coinbase` (EIP-1559)
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// gas limit for the transaction.  The default is the block gas limit, or the
+    /// gas left in the block if `BaseEvm::set_enforce_block_gas_limit` is enabled
+    pub gas_limit: Option<u64>,
+    /// addresses and storage slots to warm before the transaction runs (EIP-2930).
+    /// See `BaseEvm::create_access_list`
+    pub access_list: AccessList,
+}
+
+/// A transaction in a bundle.  See `BaseEvm::transact_bundle`
+#[derive(Clone, Debug, Default)]
+pub struct TxRequest {
+    pub caller: Address,
+    /// the recipient, or None to deploy a contract using `data` as the init code
+    pub to: Option<Address>,
+    pub data: Vec<u8>,
+    pub value: U256,
+    pub opts: Option<TxOptions>,
+}
+
+impl TxOptions {
+    /// Return the `gas_price` and `gas_priority_fee` to use in the `TxEnv`
+    fn gas_pricing(&self, basefee: U256) -> Result<(U256, Option<U256>)> {
+        match (
+            self.gas_price,
+            self.max_fee_per_gas,
+            self.max_priority_fee_per_gas,
+        ) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                bail!("gas_price can not be combined with EIP-1559 fees")
+            }
+            (Some(price), None, None) => Ok((price, None)),
+            (None, Some(max_fee), tip) => Ok((max_fee, Some(tip.unwrap_or(max_fee)))),
+            (None, None, Some(tip)) => Ok((basefee + tip, Some(tip))),
+            (None, None, None) => Ok((U256::ZERO, None)),
+        }
+    }
+
+    /// The priority fee per unit of gas paid to the `block.coinbase`
+    fn priority_fee(&self, basefee: U256) -> U256 {
+        match self.gas_pricing(basefee) {
+            Ok((price, tip)) => {
+                let max_tip = price.saturating_sub(basefee);
+                tip.map_or(max_tip, |tip| tip.min(max_tip))
+            }
+            Err(_) => U256::ZERO,
+        }
+    }
+}
+
+// What a checkpoint saves besides the state of the backend
+struct Checkpoint {
+    history: HistoryMark,
+    mempool: Mempool,
+    block_started: u64,
+}
+
 /// EVM that supports both in-memory and forked storage.
 pub struct BaseEvm {
     backend: StorageBackend,
     env: EnvWithHandlerCfg,
+    fee_market: Option<FeeMarket>,
+    prank: Option<Address>,
+    call_tracing: bool,
+    console_logs: bool,
+    print_console_logs: bool,
+    gas_profiling: bool,
+    gas_report: GasReport,
+    coverage: bool,
+    coverage_map: CoverageMap,
+    enforce_block_gas_limit: bool,
+    mempool: Mempool,
+    mining_mode: MiningMode,
+    history: History,
+    // timestamp the current block started at, for `MiningMode::Interval`
+    block_started: u64,
+    checkpoints: BTreeMap<u64, Checkpoint>,
 }
 
 /// Create an EVM with the in-memory database
@@ -38,18 +154,221 @@ impl BaseEvm {
     /// Create an instance of the EVM.  If fork is None it will use the in-memory database.
     /// Otherwise it will create a forked database.
     pub fn new(fork: Option<CreateFork>) -> Self {
-        let env = EnvWithHandlerCfg::default();
+        BaseEvm::new_with_spec(fork, None)
+    }
+
+    /// Create an instance of the EVM using the given hardfork (`spec_id`).
+    ///
+    /// If `spec_id` is None, the in-memory database will use `SpecId::LATEST`.  A fork
+    /// of Ethereum mainnet will use the hardfork active at the forked block, other
+    /// chains will use `SpecId::LATEST`.
+    ///
+    /// When forking, the block configuration (chain id, coinbase, etc...) is loaded
+    /// from the forked block.
+    pub fn new_with_spec(fork: Option<CreateFork>, spec_id: Option<SpecId>) -> Self {
         let backend = StorageBackend::new(fork);
-        Self { env, backend }
+        let block_config = backend.fork_block_config().unwrap_or_default();
+        let spec_id = spec_id.unwrap_or_else(|| {
+            if backend.is_fork() && block_config.chain_id == 1 {
+                mainnet_spec_id(backend.block_number, backend.timestamp)
+            } else {
+                SpecId::LATEST
+            }
+        });
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), spec_id);
+        let mut evm = Self {
+            env,
+            backend,
+            fee_market: None,
+            prank: None,
+            call_tracing: false,
+            console_logs: false,
+            print_console_logs: false,
+            gas_profiling: false,
+            gas_report: GasReport::default(),
+            coverage: false,
+            coverage_map: CoverageMap::default(),
+            enforce_block_gas_limit: false,
+            mempool: Mempool::default(),
+            mining_mode: MiningMode::default(),
+            history: History::default(),
+            block_started: 0,
+            checkpoints: BTreeMap::new(),
+        };
+        evm.set_block_config(block_config);
+        evm.install_create2_factory();
+        evm
     }
 
     /// Create an instance of the EVM and load it's state from the `SnapShot`.  This
-    /// will use the in-memory database.
+    /// will use the in-memory database, and the hardfork and block configuration
+    /// recorded in the snapshot.
     pub fn new_from_snapshot(snap: SnapShot) -> Self {
-        let env = EnvWithHandlerCfg::default();
+        let spec_id = snap
+            .spec
+            .as_deref()
+            .and_then(|name| parse_spec_id(name).ok())
+            .unwrap_or(SpecId::LATEST);
+        let block_config = snap.block.clone().unwrap_or_default();
+        let env = EnvWithHandlerCfg::new_with_spec_id(Box::default(), spec_id);
         let mut backend = StorageBackend::default();
         backend.load_snapshot(snap);
-        Self { env, backend }
+        let mut evm = Self {
+            env,
+            backend,
+            fee_market: None,
+            prank: None,
+            call_tracing: false,
+            console_logs: false,
+            print_console_logs: false,
+            gas_profiling: false,
+            gas_report: GasReport::default(),
+            coverage: false,
+            coverage_map: CoverageMap::default(),
+            enforce_block_gas_limit: false,
+            mempool: Mempool::default(),
+            mining_mode: MiningMode::default(),
+            history: History::default(),
+            block_started: 0,
+            checkpoints: BTreeMap::new(),
+        };
+        evm.set_block_config(block_config);
+        evm.install_create2_factory();
+        evm
+    }
+
+    // Add the CREATE2 factory to the in-memory database, unless it's already there.  A
+    // fork has whatever is deployed on the remote chain
+    fn install_create2_factory(&mut self) {
+        if self.backend.is_fork() {
+            return;
+        }
+        let mut account = match self.backend.basic_ref(CREATE2_FACTORY) {
+            Ok(Some(account)) if account.code_hash != KECCAK_EMPTY => return,
+            Ok(account) => account.unwrap_or_default(),
+            Err(_) => return,
+        };
+        let bytecode = Bytecode::new_raw(Bytes::from_static(CREATE2_FACTORY_CODE)).to_checked();
+        account.code_hash = bytecode.hash_slow();
+        account.code = Some(bytecode);
+        self.backend.insert_account_info(CREATE2_FACTORY, account);
+    }
+
+    /// Return the hardfork used by the EVM
+    pub fn spec_id(&self) -> SpecId {
+        self.env.handler_cfg.spec_id
+    }
+
+    /// Change the hardfork used by the EVM for all future transactions
+    pub fn set_spec_id(&mut self, spec_id: SpecId) {
+        self.env.handler_cfg.spec_id = spec_id;
+    }
+
+    /// Return the current block and chain configuration
+    pub fn block_config(&self) -> BlockConfig {
+        BlockConfig {
+            chain_id: self.env.cfg.chain_id,
+            coinbase: self.env.block.coinbase,
+            gas_limit: self.env.block.gas_limit.saturating_to(),
+            basefee: self.backend.basefee,
+            prevrandao: self.env.block.prevrandao.unwrap_or_default(),
+            difficulty: self.env.block.difficulty,
+        }
+    }
+
+    /// Set the block and chain configuration used for all future transactions
+    pub fn set_block_config(&mut self, config: BlockConfig) {
+        self.env.cfg.chain_id = config.chain_id;
+        self.env.block.coinbase = config.coinbase;
+        self.env.block.gas_limit = U256::from(config.gas_limit);
+        self.backend.basefee = config.basefee;
+        self.env.block.prevrandao = Some(config.prevrandao);
+        self.env.block.difficulty = config.difficulty;
+    }
+
+    /// Return the fee market settings, if enabled
+    pub fn fee_market(&self) -> Option<FeeMarket> {
+        self.fee_market
+    }
+
+    /// Enable (`Some`) or disable (`None`) the EIP-1559 fee market. When enabled,
+    /// `update_block` adjusts `block.basefee` based on the gas used in the block.
+    /// Otherwise the base fee only changes with `set_block_config`.
+    pub fn set_fee_market(&mut self, market: Option<FeeMarket>) {
+        self.fee_market = market;
+    }
+
+    /// Return the current `block.basefee`
+    pub fn basefee(&self) -> U256 {
+        self.backend.basefee
+    }
+
+    /// Return the amount of gas used by committed transactions in the current block
+    pub fn block_gas_used(&self) -> u64 {
+        self.backend.block_gas_used
+    }
+
+    /// Return when blocks are mined
+    pub fn mining_mode(&self) -> MiningMode {
+        self.mining_mode
+    }
+
+    /// Set when blocks are mined.  See `MiningMode`
+    pub fn set_mining_mode(&mut self, mode: MiningMode) {
+        self.mining_mode = mode;
+        self.block_started = self.backend.timestamp;
+    }
+
+    /// Set the order pending transactions are mined in. Default: FIFO
+    pub fn set_tx_ordering(&mut self, ordering: TxOrdering) {
+        self.mempool.ordering = ordering;
+    }
+
+    /// Return the hashes of the transactions waiting to be mined
+    pub fn pending_transactions(&self) -> Vec<B256> {
+        self.mempool.hashes()
+    }
+
+    /// Return a committed transaction by hash. See `CallResult::tx_hash`
+    pub fn get_transaction(&self, hash: B256) -> Option<&Transaction> {
+        self.history.transaction(&hash)
+    }
+
+    /// Return the receipt of a committed transaction by hash
+    pub fn get_receipt(&self, hash: B256) -> Option<&Receipt> {
+        self.history.receipt(&hash)
+    }
+
+    /// Return a block by number.  Only blocks that have been mined (or advanced with
+    /// `update_block`) are available.  Transactions are committed to the current block
+    /// until then.
+    pub fn get_block(&self, number: u64) -> Option<&Block> {
+        self.history.block(number)
+    }
+
+    /// Return the logs emitted by committed transactions that match the `filter`,
+    /// in the order they were emitted.  Logs of reverted transactions are not included.
+    pub fn get_logs(&self, filter: &LogFilter) -> Vec<IndexedLog> {
+        self.history.logs(filter)
+    }
+
+    /// Register a precompile at `address`, replacing any precompile already there.
+    /// Calls to the address run the precompile instead of any code at the address.
+    /// Works in both in-memory and fork mode.
+    pub fn register_precompile(
+        &mut self,
+        address: Address,
+        precompile: impl CustomPrecompile + 'static,
+    ) {
+        self.backend
+            .precompiles
+            .insert(address, Arc::new(precompile));
+    }
+
+    /// Remove a precompile added with `register_precompile`.  Returns false if there
+    /// wasn't one at `address`
+    pub fn remove_precompile(&mut self, address: Address) -> bool {
+        self.backend.precompiles.remove(&address).is_some()
     }
 
     /// Create an account for the given `user` with an optional balance (`amount`).
@@ -72,29 +391,237 @@ impl BaseEvm {
             .unwrap_or_default())
     }
 
-    /*
-    /// Set the balance for the given `address` with the given `amount`
-    pub fn set_balance(&mut self, address: Address, amount: U256) -> Result<&mut Self> {
+    /// Set the balance for the given `address` with the given `amount`.
+    /// Other account fields are not changed.
+    pub fn set_balance(&mut self, address: Address, amount: U256) -> Result<()> {
         let mut account = self.backend.basic_ref(address)?.unwrap_or_default();
         account.balance = amount;
+        self.backend.insert_account_info(address, account);
+        Ok(())
+    }
+
+    /// Return the nonce for the given `address`
+    pub fn get_nonce(&self, address: Address) -> Result<u64> {
+        Ok(self
+            .backend
+            .basic_ref(address)?
+            .map(|acc| acc.nonce)
+            .unwrap_or_default())
+    }
 
+    /// Set the nonce for the given `address`. Other account fields are not changed.
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) -> Result<()> {
+        let mut account = self.backend.basic_ref(address)?.unwrap_or_default();
+        account.nonce = nonce;
         self.backend.insert_account_info(address, account);
-        Ok(self)
+        Ok(())
+    }
+
+    /// Return the runtime bytecode at the given `address`.  Empty if the
+    /// account has no code.
+    pub fn get_code(&self, address: Address) -> Result<Bytes> {
+        let Some(account) = self.backend.basic_ref(address)? else {
+            return Ok(Bytes::new());
+        };
+        let code = match account.code {
+            Some(code) => code,
+            None => self.backend.code_by_hash_ref(account.code_hash)?,
+        };
+        Ok(code.original_bytes())
+    }
+
+    /// Set (etch) the runtime bytecode at the given `address`. The constructor
+    /// is not run. Balance, nonce, and storage are not changed.  Empty `code`
+    /// removes the code from the account.
+    pub fn set_code(&mut self, address: Address, code: Bytes) -> Result<()> {
+        let mut account = self.backend.basic_ref(address)?.unwrap_or_default();
+        if code.is_empty() {
+            account.code = None;
+            account.code_hash = KECCAK_EMPTY;
+        } else {
+            let bytecode = Bytecode::new_raw(code).to_checked();
+            account.code_hash = bytecode.hash_slow();
+            account.code = Some(bytecode);
+        }
+        self.backend.insert_account_info(address, account);
+        Ok(())
+    }
+
+    /// Return the value of the storage `slot` for the given `address`
+    pub fn get_storage_at(&self, address: Address, slot: U256) -> Result<U256> {
+        Ok(self.backend.storage_ref(address, slot)?)
+    }
+
+    /// Set the value of the storage `slot` for the given `address`
+    pub fn set_storage_at(&mut self, address: Address, slot: U256, value: U256) -> Result<()> {
+        self.backend.insert_account_storage(address, slot, value)?;
+        Ok(())
+    }
+
+    /// Impersonate `caller`.  All transactions, including read calls, will use `caller`
+    /// for `tx.origin` and `msg.sender` until `stop_prank` is called. This overrides
+    /// the caller passed to a transaction.
+    pub fn start_prank(&mut self, caller: Address) {
+        self.prank = Some(caller);
+    }
+
+    /// Stop impersonating the caller set by `start_prank`
+    pub fn stop_prank(&mut self) {
+        self.prank = None;
+    }
+
+    /// Return the address being impersonated, if any
+    pub fn prank(&self) -> Option<Address> {
+        self.prank
+    }
+
+    /// Enable or disable the call tracer.  When enabled, the tree of calls made by
+    /// each transaction is returned in `CallResult::trace`.
+    pub fn set_call_tracing(&mut self, enabled: bool) {
+        self.call_tracing = enabled;
+    }
+
+    /// Is the call tracer enabled?
+    pub fn call_tracing(&self) -> bool {
+        self.call_tracing
+    }
+
+    /// Enable or disable capturing messages logged with Hardhat's `console.log`.  When
+    /// enabled, they're returned in `CallResult::console_logs`, and also printed to
+    /// stdout if `print` is true.  Printing includes the messages of transactions that
+    /// fail.  Disabled by default.
+    pub fn set_console_logs(&mut self, enabled: bool, print: bool) {
+        self.console_logs = enabled;
+        self.print_console_logs = enabled && print;
+    }
+
+    /// Are `console.log` messages captured?
+    pub fn console_logs(&self) -> bool {
+        self.console_logs
+    }
+
+    /// Enable or disable enforcing the block gas limit.  When enabled, the gas limit of a
+    /// transaction must fit in the gas left in the current block: `block.gaslimit` less
+    /// the gas used by transactions committed in the block.  When disabled (the default),
+    /// a transaction may use any gas limit.
+    pub fn set_enforce_block_gas_limit(&mut self, enabled: bool) {
+        self.enforce_block_gas_limit = enabled;
+    }
+
+    /// Is the block gas limit enforced?
+    pub fn enforce_block_gas_limit(&self) -> bool {
+        self.enforce_block_gas_limit
+    }
+
+    /// Enable or disable the gas profiler.  When enabled, the gas used by each
+    /// transaction is added to the report returned by `gas_report`.
+    pub fn set_gas_profiling(&mut self, enabled: bool) {
+        self.gas_profiling = enabled;
+    }
+
+    /// Is the gas profiler enabled?
+    pub fn gas_profiling(&self) -> bool {
+        self.gas_profiling
+    }
+
+    /// Return the gas used by the transactions run while the profiler was enabled
+    pub fn gas_report(&self) -> &GasReport {
+        &self.gas_report
+    }
+
+    /// Clear the gas report
+    pub fn reset_gas_report(&mut self) {
+        self.gas_report = GasReport::default();
+    }
+
+    /// Enable or disable coverage.  When enabled, the instructions and branches executed
+    /// by each transaction are recorded for `coverage_lcov`.  Constructors aren't covered.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.coverage = enabled;
+    }
+
+    /// Is coverage enabled?
+    pub fn coverage(&self) -> bool {
+        self.coverage
+    }
+
+    /// Clear the coverage
+    pub fn reset_coverage(&mut self) {
+        self.coverage_map = CoverageMap::default();
+    }
+
+    /// Create an LCOV report of the lines and branches of the `contracts` executed by the
+    /// transactions run while coverage was enabled
+    pub fn coverage_lcov(&self, contracts: &[ContractSources]) -> Result<String> {
+        self.coverage_map.lcov(contracts)
     }
-    */
 
     /// Create a snapshot of the current database. This can be used to reload state.
     pub fn create_snapshot(&self) -> Result<SnapShot> {
-        self.backend.create_snapshot()
+        let mut snap = self.backend.create_snapshot()?;
+        snap.spec = Some(spec_id_name(self.spec_id()).into());
+        snap.block = Some(self.block_config());
+        Ok(snap)
+    }
+
+    /// Save the current state in memory and return an id that can be
+    /// used with `revert_to` or `discard`. Checkpoints may be nested.
+    ///
+    /// This is much cheaper than `create_snapshot` when exploring many
+    /// alternative branches from the same state.
+    pub fn checkpoint(&mut self) -> u64 {
+        let id = self.backend.checkpoint();
+        self.checkpoints.insert(
+            id,
+            Checkpoint {
+                history: self.history.mark(),
+                mempool: self.mempool.clone(),
+                block_started: self.block_started,
+            },
+        );
+        id
+    }
+
+    /// Restore the state saved by checkpoint `id`, including the transactions, receipts
+    /// and blocks, and the mempool.  The checkpoint and any checkpoints created after it
+    /// are removed.  Returns false if the checkpoint does not exist.
+    pub fn revert_to(&mut self, id: u64) -> bool {
+        if !self.backend.revert_to(id) {
+            return false;
+        }
+        if let Some((_, checkpoint)) = self.checkpoints.split_off(&id).pop_first() {
+            self.history.rollback(checkpoint.history);
+            self.mempool = checkpoint.mempool;
+            self.block_started = checkpoint.block_started;
+        }
+        true
+    }
+
+    /// Drop checkpoint `id` without changing state. Returns false if the
+    /// checkpoint does not exist.
+    pub fn discard(&mut self, id: u64) -> bool {
+        self.checkpoints.remove(&id);
+        self.backend.discard(id)
     }
 
     /// Deploy a contract returning the contract's address.
     /// If `value` is specified, the constructor must be `payable`.
-    pub fn deploy(&mut self, caller: Address, data: Vec<u8>, value: U256) -> Result<Address> {
-        let mut env = self.build_env(Some(caller), TransactTo::create(), data.into(), value);
-        let result = self.backend.run_transact(&mut env)?;
-        let mut call_results = process_call_result(result)?;
-        self.commit(&mut call_results);
+    ///
+    /// The transaction bypasses the mempool: it's executed and committed in the current
+    /// block right away, ahead of any pending transactions.  A block may then be mined,
+    /// depending on the mining mode.  See `set_mining_mode`
+    pub fn deploy(
+        &mut self,
+        caller: Address,
+        data: Vec<u8>,
+        value: U256,
+        opts: Option<TxOptions>,
+    ) -> Result<Address> {
+        let mut env =
+            self.build_env(Some(caller), TransactTo::create(), data.into(), value, opts)?;
+        let mut call_results = self.execute(&mut env)?;
+        self.commit(&env, None, &mut call_results);
+        self.mine_after_commit();
 
         match call_results.address {
             Some(addr) => Ok(addr),
@@ -102,10 +629,105 @@ impl BaseEvm {
         }
     }
 
+    /// Deploy a contract at `address`.  The constructor (`init_code`) is run at `address`
+    /// by `caller`, or the impersonated caller if any, so `address(this)`, immutables and
+    /// storage are the same as if the contract had been created there.  The runtime code
+    /// it returns becomes the code at `address`.  `value` is paid by the caller, and any
+    /// other state changes made by the constructor are kept, but no transaction is
+    /// recorded and the nonce of the caller isn't used.  Fails if the constructor doesn't
+    /// return any runtime code.
+    ///
+    /// The constructor is run as a call to `address` with the init code as its code, so
+    /// while it runs, the code size of `address` is that of the init code instead of 0.
+    pub fn deploy_at(
+        &mut self,
+        caller: Address,
+        address: Address,
+        init_code: Vec<u8>,
+        value: U256,
+    ) -> Result<()> {
+        if !self.get_code(address)?.is_empty() {
+            bail!("a contract is already deployed at {}", address)
+        }
+        let mut env = self.build_env(
+            Some(caller),
+            TransactTo::call(address),
+            Bytes::new(),
+            value,
+            None,
+        )?;
+        let caller = env.tx.caller;
+        let nonce = self.get_nonce(caller)?;
+
+        let id = self.checkpoint();
+        let run = |evm: &mut Self| -> Result<CallResult> {
+            evm.set_code(address, init_code.into())?;
+            // like an account created by CREATE, the nonce starts at 1 (EIP-161), so
+            // contracts created by the constructor get the same addresses
+            let contract_nonce = evm.get_nonce(address)?;
+            evm.set_nonce(address, contract_nonce.max(1))?;
+            let result = evm.execute(&mut env)?;
+            if result.result.is_empty() {
+                bail!("no code was deployed at {}", address)
+            }
+            Ok(result)
+        };
+        let result = match run(self) {
+            Ok(result) => result,
+            Err(err) => {
+                self.revert_to(id);
+                return Err(err);
+            }
+        };
+        self.discard(id);
+
+        if let Some(changes) = result.state_changeset {
+            self.backend.commit(changes);
+        }
+        self.set_nonce(caller, nonce)?;
+        self.set_code(address, result.result)
+    }
+
+    /// Deploy a contract with CREATE2 through `CREATE2_FACTORY`, returning its address.
+    /// The address only depends on the `salt` and the init code (`data`), see
+    /// `create2_address`, so it's the same as on any chain with the factory.  Fails if
+    /// the constructor doesn't return any runtime code.
+    pub fn deploy_create2(
+        &mut self,
+        caller: Address,
+        salt: B256,
+        data: Vec<u8>,
+        value: U256,
+        opts: Option<TxOptions>,
+    ) -> Result<Address> {
+        let address = create2_address(CREATE2_FACTORY, salt, &data);
+        if self.get_code(CREATE2_FACTORY)?.is_empty() {
+            bail!("CREATE2 factory is not deployed at {}", CREATE2_FACTORY)
+        }
+        if !self.get_code(address)?.is_empty() {
+            bail!("a contract is already deployed at {}", address)
+        }
+        let input = [salt.as_slice(), &data].concat();
+        let result = self.transact_commit(caller, CREATE2_FACTORY, input, value, opts)?;
+        // the factory returns the address of the contract
+        if result.result.as_ref() != address.as_slice() {
+            bail!("CREATE2 factory did not deploy to {}", address)
+        }
+        if self.get_code(address)?.is_empty() {
+            bail!("no code was deployed at {}", address)
+        }
+        Ok(address)
+    }
+
     /// Transfer `value` from `caller` -> `to`
-    pub fn transfer(&mut self, caller: Address, to: Address, value: U256) -> Result<()> {
-        let _ = self.transact_commit(caller, to, vec![], value)?;
-        Ok(())
+    pub fn transfer(
+        &mut self,
+        caller: Address,
+        to: Address,
+        value: U256,
+        opts: Option<TxOptions>,
+    ) -> Result<CallResult> {
+        self.transact_commit(caller, to, vec![], value, opts)
     }
 
     /* TODO Remove?
@@ -125,17 +747,25 @@ impl BaseEvm {
     */
 
     /// Write call to a contact.  Send a transaction where any state changes are persisted to the underlying database.
+    ///
+    /// Use `opts` to pay for gas. Fees are deducted from the `caller` and the tip is paid to `block.coinbase`.
+    ///
+    /// The transaction bypasses the mempool: it's executed and committed in the current
+    /// block right away, ahead of any pending transactions.  A block may then be mined,
+    /// depending on the mining mode.  See `set_mining_mode`
     pub fn transact_commit(
         &mut self,
         caller: Address,
         to: Address,
         data: Vec<u8>,
         value: U256,
+        opts: Option<TxOptions>,
     ) -> Result<CallResult> {
-        let mut env = self.build_env(Some(caller), TransactTo::call(to), data.into(), value);
-        let result = self.backend.run_transact(&mut env)?;
-        let mut call_results = process_call_result(result)?;
-        self.commit(&mut call_results);
+        let mut env =
+            self.build_env(Some(caller), TransactTo::call(to), data.into(), value, opts)?;
+        let mut call_results = self.execute(&mut env)?;
+        self.commit(&env, None, &mut call_results);
+        self.mine_after_commit();
 
         Ok(call_results)
     }
@@ -149,18 +779,27 @@ impl BaseEvm {
         value: U256,
     ) -> Result<<T as SolCall>::Return> {
         let data = args.abi_encode();
-        let result = self.transact_call(to, data, value)?;
+        let result = self.transact_call(to, data, value, None)?;
         T::abi_decode_returns(&result.result, true)
             .map_err(|e| anyhow!("transact call sol error: {:?}", e))
     }
     */
 
     /// Read call to a contract.  Send a transaction but any state changes are NOT persisted to the
-    /// database.   
-    pub fn transact_call(&mut self, to: Address, data: Vec<u8>, value: U256) -> Result<CallResult> {
-        let mut env = self.build_env(None, TransactTo::call(to), data.into(), value);
-        let result = self.backend.run_transact(&mut env)?;
-        process_call_result(result)
+    /// database.
+    ///
+    /// `caller` is used for `msg.sender` and `tx.origin`. If None, `Address::ZERO` is used.
+    pub fn transact_call(
+        &mut self,
+        to: Address,
+        data: Vec<u8>,
+        value: U256,
+        caller: Option<Address>,
+    ) -> Result<CallResult> {
+        let mut env = self.build_env(caller, TransactTo::call(to), data.into(), value, None)?;
+        // like `eth_call`, a read is not limited by the gas used in the block
+        env.tx.gas_limit = self.env.block.gas_limit.saturating_to();
+        self.execute(&mut env)
     }
 
     /// Simulate a `transact_commit` without actually committing/changing state.
@@ -170,19 +809,381 @@ impl BaseEvm {
         to: Address,
         data: Vec<u8>,
         value: U256,
+        opts: Option<TxOptions>,
     ) -> Result<CallResult> {
-        let mut env = self.build_env(Some(caller), TransactTo::call(to), data.into(), value);
-        let result = self.backend.run_transact(&mut env)?;
-        process_call_result(result)
+        let mut env =
+            self.build_env(Some(caller), TransactTo::call(to), data.into(), value, opts)?;
+        self.execute(&mut env)
+    }
+
+    /// Execute the transactions in order.  The state changes of each transaction are
+    /// committed before the next one runs.  Returns the result of each transaction.
+    ///
+    /// If `all_or_nothing` is true and a transaction fails, the state is restored to what
+    /// it was before the bundle and the error is returned with a `BundleError` context.
+    /// Otherwise, a failed transaction is skipped and its error is returned in place of
+    /// the result.
+    pub fn transact_bundle(
+        &mut self,
+        txs: Vec<TxRequest>,
+        all_or_nothing: bool,
+    ) -> Result<Vec<Result<CallResult>>> {
+        let id = self.checkpoint();
+        let results = self.run_bundle(txs, all_or_nothing);
+        match results {
+            Ok(_) => self.discard(id),
+            Err(_) => self.revert_to(id),
+        };
+        if results.is_ok() {
+            self.mine_after_commit();
+        }
+        results
+    }
+
+    /// Same as `transact_bundle` without changing state.  Each transaction sees the
+    /// changes made by the transactions before it.
+    pub fn simulate_bundle(
+        &mut self,
+        txs: Vec<TxRequest>,
+        all_or_nothing: bool,
+    ) -> Result<Vec<Result<CallResult>>> {
+        let id = self.checkpoint();
+        let results = self.run_bundle(txs, all_or_nothing);
+        self.revert_to(id);
+        results
+    }
+
+    /// Generate an EIP-2930 access list for the transaction without committing any state changes.
+    /// Same as `eth_createAccessList`.
+    ///
+    /// Returns the addresses and storage slots accessed, and the gas used with and without the
+    /// access list.  If the transaction fails, e.g. it reverts, the addresses and slots accessed
+    /// until it failed are returned with the error.
+    pub fn create_access_list(
+        &mut self,
+        caller: Address,
+        to: Address,
+        data: Vec<u8>,
+        value: U256,
+    ) -> Result<AccessListResult> {
+        let mut env = self.build_env(
+            Some(caller),
+            TransactTo::call(to),
+            data.clone().into(),
+            value,
+            None,
+        )?;
+        let mut inspector = InspectorStack {
+            access_list: Some(AccessListTracer::default()),
+            ..Default::default()
+        };
+        let result = self.backend.run_transact(&mut env, &mut inspector)?;
+        let (gas_used_without_access_list, error) = match process_call_result(result, &env) {
+            Ok(result) => (result.gas_used, None),
+            Err((err @ ExecutionError::Database(_), _)) => return Err(err.into()),
+            Err((err, _)) => (err.gas_used(), Some(err)),
+        };
+        let access_list = inspector
+            .access_list
+            .map(AccessListTracer::into_access_list)
+            .unwrap_or_default();
+
+        let opts = TxOptions {
+            access_list: access_list.clone(),
+            ..Default::default()
+        };
+        let mut env = self.build_env(
+            Some(caller),
+            TransactTo::call(to),
+            data.into(),
+            value,
+            Some(opts),
+        )?;
+        let result = self
+            .backend
+            .run_transact(&mut env, &mut InspectorStack::default())?;
+        let gas_used = match process_call_result(result, &env) {
+            Ok(result) => result.gas_used,
+            Err((err @ ExecutionError::Database(_), _)) => return Err(err.into()),
+            Err((err, _)) => err.gas_used(),
+        };
+
+        Ok(AccessListResult {
+            access_list,
+            gas_used,
+            gas_used_without_access_list,
+            error,
+        })
+    }
+
+    /// Estimate the gas limit needed for the transaction to succeed.  Same as `eth_estimateGas`.
+    ///
+    /// Binary search for the smallest gas limit that works.  This is usually more than the gas
+    /// used: refunds are only paid at the end of the transaction, and a call can only forward
+    /// 63/64 of the remaining gas. Returns the error if the transaction fails with the block
+    /// gas limit.
+    pub fn estimate_gas(
+        &mut self,
+        caller: Address,
+        to: Address,
+        data: Vec<u8>,
+        value: U256,
+    ) -> Result<u64> {
+        let env = self.build_env(Some(caller), TransactTo::call(to), data.into(), value, None)?;
+        let mut hi = env.tx.gas_limit;
+        let result = self.call_with_gas_limit(&env, hi)?;
+
+        // the gas limit must cover the gas used before the refund
+        let mut lo = (result.gas_used + result.gas_refunded).saturating_sub(1);
+        // most transactions succeed with a little more than the gas used.  Try
+        // that first to cut down the search
+        let optimistic = (lo + 1 + CALL_STIPEND) * 64 / 63;
+        if optimistic < hi && self.succeeds(&env, optimistic)? {
+            hi = optimistic;
+        }
+        while lo + 1 < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.succeeds(&env, mid)? {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+        Ok(hi)
+    }
+
+    /// Trace each opcode executed by the transaction without committing any state changes.
+    /// Unlike `simulate`, a transaction that reverts is still traced: see `StructLogTrace::failed`.
+    ///
+    /// The result serializes to the same JSON as geth's `debug_traceTransaction` with the
+    /// default struct logger.  Only calls are traced, there's no replay of committed
+    /// transactions: to compare with a node's trace of a transaction, fork at the block
+    /// before it and trace the same call.
+    pub fn debug_trace_call(
+        &mut self,
+        caller: Address,
+        to: Address,
+        data: Vec<u8>,
+        value: U256,
+        opts: Option<TxOptions>,
+        config: StructLoggerConfig,
+    ) -> Result<StructLogTrace> {
+        let mut env =
+            self.build_env(Some(caller), TransactTo::call(to), data.into(), value, opts)?;
+        let mut inspector = InspectorStack {
+            struct_logger: Some(StructLogger::new(config)),
+            ..Default::default()
+        };
+        let ResultAndState { result, .. } = self.backend.run_transact(&mut env, &mut inspector)?;
+
+        Ok(StructLogTrace {
+            gas: result.gas_used(),
+            failed: !result.is_success(),
+            return_value: result.output().map(hex::encode).unwrap_or_default(),
+            struct_logs: inspector
+                .struct_logger
+                .map(StructLogger::into_logs)
+                .unwrap_or_default(),
+        })
     }
 
     /// Advance `block.number` and `block.timestamp`. Set `interval` to the
     /// amount of time in seconds you want to advance the timestamp. Block number
     /// will be automatically incremented.
     ///
+    /// If the fee market is enabled, `block.basefee` is adjusted for the new block
+    /// based on the gas used in the current block.
+    ///
     /// Must be manually called.
     pub fn update_block(&mut self, interval: u64) {
+        self.seal_block(interval);
+    }
+
+    /// Return `block.number` of the current block
+    pub fn block_number(&self) -> u64 {
+        self.backend.block_number
+    }
+
+    /// Return `block.timestamp` of the current block
+    pub fn timestamp(&self) -> u64 {
+        self.backend.timestamp
+    }
+
+    /// Set `block.timestamp` of the current block.  It may go back in time.  Blocks
+    /// mined after this are `interval` seconds apart as usual.
+    ///
+    /// With `MiningMode::Interval`, if `timestamp` is at least `interval` seconds after
+    /// the start of the current block, the block is mined first and the next block
+    /// starts at `timestamp`.
+    pub fn warp(&mut self, timestamp: u64) {
+        if let MiningMode::Interval(interval) = self.mining_mode {
+            if timestamp >= self.block_started.saturating_add(interval) {
+                self.mine_block(interval);
+                self.block_started = timestamp;
+            }
+        }
+        self.backend.timestamp = timestamp;
+    }
+
+    /// Set `block.number` of the current block.  Like `warp`, it may go back, or skip
+    /// ahead: blocks mined after this are numbered from `block_number`.  No block is
+    /// mined, so the transactions already committed in the current block move to the
+    /// new number.  If a block number is mined again, `get_block` returns the latest
+    /// block with that number.
+    pub fn roll(&mut self, block_number: u64) {
+        self.backend.block_number = block_number;
+        self.history.set_current_block_number(block_number);
+    }
+
+    /// Queue a transaction in the mempool, returning its hash.  It's executed when the
+    /// next block is mined.  See `mine_block` and `set_mining_mode`.  If the transaction
+    /// is mined right away and dropped because it's invalid, the error is returned.
+    pub fn send_transaction(&mut self, tx: TxRequest) -> Result<B256> {
+        let block_gas_limit: u64 = self.env.block.gas_limit.saturating_to();
+        if let Some(gas_limit) = tx.opts.as_ref().and_then(|opts| opts.gas_limit) {
+            if gas_limit > block_gas_limit {
+                bail!("gas limit of {gas_limit} exceeds the block gas limit: {block_gas_limit}");
+            }
+        }
+        let hash = self.mempool.push(tx);
+        let dropped = match self.mining_mode {
+            MiningMode::Auto(block_time) => self.mine_block(block_time).1,
+            MiningMode::Batch {
+                transactions,
+                block_time,
+            } => {
+                let count = self.mempool.hashes().len() + self.history.current_transactions();
+                if count as u64 >= transactions {
+                    self.mine_block(block_time).1
+                } else {
+                    vec![]
+                }
+            }
+            MiningMode::Manual | MiningMode::Interval(_) => vec![],
+        };
+        match dropped.into_iter().find(|dropped| dropped.hash == hash) {
+            Some(dropped) => Err(dropped.error),
+            None => Ok(hash),
+        }
+    }
+
+    /// Mine a block. The pending transactions are executed in order (see `set_tx_ordering`),
+    /// then the block is sealed and a new block is started `interval` seconds later.
+    ///
+    /// Transactions that don't fit in the gas left in the block stay in the mempool.  A
+    /// transaction without a gas limit can use all the gas left.  A transaction that fails
+    /// uses gas in the block and the caller pays the fee and uses the nonce, but none of
+    /// its other state changes are committed.  Invalid transactions, e.g. with a gas price
+    /// below the base fee, are dropped: they're returned with the block and their error.
+    pub fn mine_block(&mut self, interval: u64) -> (Block, Vec<DroppedTx>) {
+        let basefee = self.backend.basefee;
+        let block_gas_limit: u64 = self.env.block.gas_limit.saturating_to();
+        let pending = self.mempool.take(|tx| {
+            tx.opts
+                .as_ref()
+                .map_or(U256::ZERO, |opts| opts.priority_fee(basefee))
+        });
+
+        let mut skipped = Vec::new();
+        let mut dropped = Vec::new();
+        for tx in pending {
+            let available = block_gas_limit.saturating_sub(self.backend.block_gas_used);
+            let gas_limit = tx.request.opts.as_ref().and_then(|opts| opts.gas_limit);
+            if gas_limit.unwrap_or(available) > available || available < MIN_TRANSACTION_GAS {
+                skipped.push(tx);
+                continue;
+            }
+
+            let mut request = tx.request.clone();
+            request
+                .opts
+                .get_or_insert_with(TxOptions::default)
+                .gas_limit = Some(gas_limit.unwrap_or(available));
+            // invalid transactions, e.g. bad gas pricing, are dropped
+            let mut env = match self.request_env(request) {
+                Ok(env) => env,
+                Err(error) => {
+                    dropped.push(DroppedTx {
+                        hash: tx.hash,
+                        error,
+                    });
+                    continue;
+                }
+            };
+            match self.run(&mut env) {
+                Ok(Ok(mut result)) => self.commit(&env, Some(tx.hash), &mut result),
+                // the transaction failed: the caller still pays the fee and uses the nonce
+                Ok(Err((err, changes))) => {
+                    self.backend.commit(changes);
+                    let gas_used = err.gas_used();
+                    self.backend.block_gas_used += gas_used;
+                    let transaction =
+                        Transaction::from_env(tx.hash, &env.tx, self.backend.block_number);
+                    let receipt = Receipt::new(
+                        &transaction,
+                        false,
+                        gas_used,
+                        self.backend.block_gas_used,
+                        env.effective_gas_price(),
+                        None,
+                        vec![],
+                    );
+                    self.history.record(transaction, receipt);
+                }
+                Err(error) => dropped.push(DroppedTx {
+                    hash: tx.hash,
+                    error,
+                }),
+            }
+        }
+        self.mempool.requeue(skipped);
+        (self.seal_block(interval), dropped)
+    }
+
+    // Record the header of the current block and start a new one
+    fn seal_block(&mut self, interval: u64) -> Block {
+        let header = BlockHeader {
+            number: self.backend.block_number,
+            timestamp: self.backend.timestamp,
+            gas_used: self.backend.block_gas_used,
+            gas_limit: self.env.block.gas_limit.saturating_to(),
+            base_fee: self.backend.basefee,
+            parent_hash: self
+                .history
+                .latest_block()
+                .map(|block| block.header.hash())
+                .unwrap_or_default(),
+        };
+        let block = self.history.seal(header);
+
+        if let Some(market) = self.fee_market {
+            self.backend.basefee = market.next_base_fee(
+                self.backend.basefee,
+                self.backend.block_gas_used,
+                self.env.block.gas_limit.saturating_to(),
+            );
+        }
         self.backend.update_block_info(interval);
+        self.block_started = self.backend.timestamp;
+        block
+    }
+
+    // Mine after a transaction is committed outside of `mine_block`, depending on the mode
+    fn mine_after_commit(&mut self) {
+        match self.mining_mode {
+            MiningMode::Manual | MiningMode::Interval(_) => {}
+            MiningMode::Auto(block_time) => {
+                self.mine_block(block_time);
+            }
+            MiningMode::Batch {
+                transactions,
+                block_time,
+            } => {
+                if self.history.current_transactions() as u64 >= transactions {
+                    self.mine_block(block_time);
+                }
+            }
+        }
     }
 
     fn build_env(
@@ -191,36 +1192,188 @@ impl BaseEvm {
         transact_to: TransactTo,
         data: Bytes,
         value: U256,
-    ) -> EnvWithHandlerCfg {
+        opts: Option<TxOptions>,
+    ) -> Result<EnvWithHandlerCfg> {
         let blkn = self.backend.block_number;
         let ts = self.backend.timestamp;
+        let opts = opts.unwrap_or_default();
+        let basefee = self.backend.basefee;
+        let (gas_price, gas_priority_fee) = opts.gas_pricing(basefee)?;
 
-        let env = Env {
-            cfg: self.env.cfg.clone(),
-            block: BlockEnv {
-                basefee: U256::ZERO,
+        let block_gas_limit: u64 = self.env.block.gas_limit.saturating_to();
+        let available = match self.enforce_block_gas_limit {
+            true => block_gas_limit.saturating_sub(self.backend.block_gas_used),
+            false => block_gas_limit,
+        };
+        let gas_limit = opts.gas_limit.unwrap_or(available);
+        if self.enforce_block_gas_limit && gas_limit > available {
+            return Err(ExecutionError::GasLimitExceedsBlock {
+                gas_limit,
+                available,
+            }
+            .into());
+        }
+
+        let mut env = Env {
+            cfg: self.env.cfg.clone(),
+            block: BlockEnv {
                 timestamp: U256::from(ts),
                 number: U256::from(blkn),
+                basefee,
                 ..self.env.block.clone()
             },
             tx: TxEnv {
-                caller: caller.unwrap_or(Address::ZERO),
+                caller: self.prank.or(caller).unwrap_or(Address::ZERO),
                 transact_to,
                 data,
                 value,
-                gas_price: U256::ZERO,
-                gas_priority_fee: None,
+                gas_limit,
+                gas_price,
+                gas_priority_fee,
+                access_list: opts.access_list,
                 ..self.env.tx.clone()
             },
         };
+        // the transaction is free. Skip the check that `gas_price >= block.basefee`
+        env.cfg.disable_base_fee = env.tx.gas_price.is_zero();
+        // revm checks the gas limit is <= the block gas limit
+        env.cfg.disable_block_gas_limit = !self.enforce_block_gas_limit;
+        // allow any address to be the caller, including contracts. See `start_prank`
+        env.cfg.disable_eip3607 = true;
+
+        Ok(EnvWithHandlerCfg::new_with_spec_id(
+            Box::new(env),
+            self.env.handler_cfg.spec_id,
+        ))
+    }
+
+    // Run the transaction with any enabled inspectors. State changes are not committed.
+    fn execute(&mut self, env: &mut EnvWithHandlerCfg) -> Result<CallResult> {
+        Ok(self.run(env)?.map_err(|(err, _)| err)?)
+    }
+
+    // Like `execute`, but a transaction that fails is returned with its state changes:
+    // the fee charged and the nonce of the caller.  Database errors and invalid
+    // transactions are returned in the outer result
+    fn run(
+        &mut self,
+        env: &mut EnvWithHandlerCfg,
+    ) -> Result<Result<CallResult, (ExecutionError, StateChangeSet)>> {
+        let mut inspector = InspectorStack {
+            call_tracer: self.call_tracing.then(CallTracer::default),
+            gas_profiler: self.gas_profiling.then(GasProfiler::default),
+            console_logger: self.console_logs.then(ConsoleLogger::default),
+            coverage: self.coverage.then(CoverageCollector::default),
+            ..Default::default()
+        };
+        let result = self.backend.run_transact(env, &mut inspector)?;
+        // include transactions that revert
+        if let Some(profiler) = inspector.gas_profiler.take() {
+            self.gas_report.merge(profiler.into_report());
+        }
+        if let Some(collector) = inspector.coverage.take() {
+            self.coverage_map.merge(collector.map);
+        }
+        let console_logs = inspector
+            .console_logger
+            .map(|logger| logger.messages)
+            .unwrap_or_default();
+        if self.print_console_logs {
+            console_logs
+                .iter()
+                .for_each(|message| println!("{}", message));
+        }
+        let mut call_result = match process_call_result(result, env) {
+            Ok(call_result) => call_result,
+            Err(failed) => return Ok(Err(failed)),
+        };
+        call_result.trace = inspector.call_tracer.and_then(CallTracer::into_trace);
+        call_result.console_logs = console_logs;
+        Ok(Ok(call_result))
+    }
+
+    // Run the transaction without inspectors using the given gas limit. State changes are
+    // not committed.
+    fn call_with_gas_limit(
+        &mut self,
+        env: &EnvWithHandlerCfg,
+        gas_limit: u64,
+    ) -> Result<CallResult> {
+        let mut env = env.clone();
+        env.tx.gas_limit = gas_limit;
+        let result = self
+            .backend
+            .run_transact(&mut env, &mut InspectorStack::default())?;
+        Ok(process_call_result(result, &env).map_err(|(err, _)| err)?)
+    }
 
-        EnvWithHandlerCfg::new_with_spec_id(Box::new(env), self.env.handler_cfg.spec_id)
+    // Does the transaction succeed with the given gas limit?  Database errors are returned
+    fn succeeds(&mut self, env: &EnvWithHandlerCfg, gas_limit: u64) -> Result<bool> {
+        match self.call_with_gas_limit(env, gas_limit) {
+            Ok(_) => Ok(true),
+            Err(err) => match err.downcast_ref::<ExecutionError>() {
+                Some(ExecutionError::Database(_)) => Err(err),
+                _ => Ok(false),
+            },
+        }
     }
 
-    fn commit(&mut self, result: &mut CallResult) {
+    fn request_env(&self, tx: TxRequest) -> Result<EnvWithHandlerCfg> {
+        let transact_to = tx.to.map_or_else(TransactTo::create, TransactTo::call);
+        self.build_env(
+            Some(tx.caller),
+            transact_to,
+            tx.data.into(),
+            tx.value,
+            tx.opts,
+        )
+    }
+
+    // Execute and commit each transaction. Stops at the first failure if `all_or_nothing`
+    fn run_bundle(
+        &mut self,
+        txs: Vec<TxRequest>,
+        all_or_nothing: bool,
+    ) -> Result<Vec<Result<CallResult>>> {
+        let mut results = Vec::with_capacity(txs.len());
+        for (index, tx) in txs.into_iter().enumerate() {
+            let result = self.request_env(tx).and_then(|mut env| {
+                let call_result = self.execute(&mut env)?;
+                Ok((env, call_result))
+            });
+            match result {
+                Ok((env, mut call_result)) => {
+                    self.commit(&env, None, &mut call_result);
+                    results.push(Ok(call_result));
+                }
+                Err(err) if all_or_nothing => return Err(err.context(BundleError { index })),
+                Err(err) => results.push(Err(err)),
+            }
+        }
+        Ok(results)
+    }
+
+    // Commit the state changes and record the transaction in the current block.  A hash
+    // is created if the transaction wasn't sent to the mempool.
+    fn commit(&mut self, env: &EnvWithHandlerCfg, hash: Option<B256>, result: &mut CallResult) {
         if let Some(changes) = &result.state_changeset {
             self.backend.commit(changes.clone());
         }
+        self.backend.block_gas_used += result.gas_used;
+
+        let hash = hash.unwrap_or_else(|| self.mempool.next_hash(&env.tx));
+        let transaction = Transaction::from_env(hash, &env.tx, self.backend.block_number);
+        let receipt = Receipt::new(
+            &transaction,
+            true,
+            result.gas_used,
+            self.backend.block_gas_used,
+            result.effective_gas_price,
+            result.address,
+            result.logs.clone(),
+        );
+        self.history.record(transaction, receipt);
+        result.tx_hash = Some(hash);
     }
 }
 
@@ -234,13 +1387,39 @@ pub struct CallResult {
     pub gas_used: u64,
     /// Refunded gas
     pub gas_refunded: u64,
+    /// The price paid per unit of gas
+    pub effective_gas_price: U256,
+    /// The total fee paid by the caller: `gas_used * effective_gas_price`
+    pub fee: U256,
     /// The logs emitted during the call
     pub logs: Vec<Log>,
     /// Changes made to the database
     pub state_changeset: Option<StateChangeSet>,
+    /// The tree of calls made by the transaction, if the call tracer is enabled
+    pub trace: Option<CallTrace>,
+    /// Messages logged with Hardhat's `console.log`, if enabled. See `BaseEvm::set_console_logs`
+    pub console_logs: Vec<String>,
+    /// The hash of the transaction, if it was committed.  See `BaseEvm::get_receipt`
+    pub tx_hash: Option<B256>,
+}
+
+/// The address of a contract created with CREATE by `deployer` with the given `nonce`
+pub fn create_address(deployer: Address, nonce: u64) -> Address {
+    deployer.create(nonce)
+}
+
+/// The address of a contract created with CREATE2 by `deployer`.  Use `CREATE2_FACTORY`
+/// as the deployer for `BaseEvm::deploy_create2`
+pub fn create2_address(deployer: Address, salt: B256, init_code: &[u8]) -> Address {
+    deployer.create2_from_code(salt, init_code)
 }
 
-fn process_call_result(result: ResultAndState) -> Result<CallResult> {
+// A transaction that failed is returned with its state changes
+fn process_call_result(
+    result: ResultAndState,
+    env: &EnvWithHandlerCfg,
+) -> Result<CallResult, (ExecutionError, StateChangeSet)> {
+    let effective_gas_price = env.effective_gas_price();
     let ResultAndState {
         result: exec_result,
         state: state_changeset,
@@ -254,23 +1433,46 @@ fn process_call_result(result: ResultAndState) -> Result<CallResult> {
             logs,
             ..
         } => (gas_refunded, gas_used, output, logs),
-        ExecutionResult::Revert { gas_used, output } => match decode_revert_reason(&output) {
-            Some(reason) => bail!("Reverted: {:?}. Gas used: {:?}", reason, gas_used),
-            _ => bail!("Reverted with no reason. Gas used: {:?}", gas_used),
-        },
+        ExecutionResult::Revert { gas_used, output } => {
+            return Err((
+                ExecutionError::Revert {
+                    data: output,
+                    gas_used,
+                },
+                state_changeset,
+            ))
+        }
+        ExecutionResult::Halt {
+            reason: HaltReason::OutOfGas(_),
+            gas_used,
+        } => {
+            return Err((
+                ExecutionError::OutOfGas {
+                    gas_limit: env.tx.gas_limit,
+                    gas_used,
+                },
+                state_changeset,
+            ))
+        }
         ExecutionResult::Halt { reason, gas_used } => {
-            bail!("Halted: {:?}. Gas used: {:?}", reason, gas_used)
+            return Err((ExecutionError::Halt { reason, gas_used }, state_changeset))
         }
     };
 
+    let fee = effective_gas_price * U256::from(gas_used);
     match out {
         Output::Call(result) => Ok(CallResult {
             result,
             gas_used,
             gas_refunded,
+            effective_gas_price,
+            fee,
             logs,
             address: None,
             state_changeset: Some(state_changeset),
+            trace: None,
+            console_logs: vec![],
+            tx_hash: None,
         }),
         Output::Create(data, address) => Ok(CallResult {
             result: data.clone(),
@@ -278,7 +1480,12 @@ fn process_call_result(result: ResultAndState) -> Result<CallResult> {
             gas_used,
             logs,
             gas_refunded,
+            effective_gas_price,
+            fee,
             state_changeset: Some(state_changeset),
+            trace: None,
+            console_logs: vec![],
+            tx_hash: None,
         }),
     }
 }
@@ -286,9 +1493,22 @@ fn process_call_result(result: ResultAndState) -> Result<CallResult> {
 #[cfg(test)]
 mod tests {
     use crate::core::abi::ContractAbi;
-    use crate::core::evm::BaseEvm;
+    use crate::core::block::{BlockConfig, FeeMarket};
+    use crate::core::call_tracer::CallKind;
+    use crate::core::console::CONSOLE_ADDRESS;
+    use crate::core::coverage::ContractSources;
+    use crate::core::errors::{BundleError, ExecutionError};
+    use crate::core::evm::{
+        create2_address, create_address, BaseEvm, TxOptions, TxRequest, CREATE2_FACTORY,
+    };
+    use crate::core::gas_profiler::OpcodeCategory;
+    use crate::core::mempool::{MiningMode, TxOrdering};
+    use crate::core::precompile::{CustomPrecompileError, StubPrecompile};
+    use crate::core::struct_logger::StructLoggerConfig;
     use alloy_dyn_abi::DynSolValue;
-    use alloy_primitives::{Address, U256};
+    use alloy_primitives::{hex, keccak256, Address, Bytes, B256, U256};
+    use revm::primitives::{HaltReason, PrecompileError, SpecId, TransactTo};
+    use std::collections::BTreeMap;
 
     const BYTECODE: &str = "608060405260405161032c38038061032c8339810160408190526100\
         229161003c565b600155600080546001600160a01b03191633179055610055565b6000602\
@@ -314,10 +1534,21 @@ mod tests {
         fea264697066735822122073a633ec59ee8e261bbdfefdc6d54f1d47dd6ccd6dcab4aa1eb\
         37b62d24b4c1b64736f6c63430008140033";
 
+    // Wrap `runtime` code in init code that simply returns it
+    fn init_code(runtime: &[u8]) -> Vec<u8> {
+        let len = runtime.len() as u8;
+        // PUSH1 len PUSH1 10 PUSH0 CODECOPY PUSH1 len PUSH0 RETURN
+        [
+            &[0x60, len, 0x60, 0x0a, 0x5f, 0x39, 0x60, len, 0x5f, 0xf3],
+            runtime,
+        ]
+        .concat()
+    }
+
     #[test]
     fn balances() {
         let zero = U256::from(0);
-        //let one_eth = U256::from(1e18);
+        let one_eth = U256::from(1e18);
 
         let mut evm = BaseEvm::default();
         let bob = Address::repeat_byte(23);
@@ -325,8 +1556,8 @@ mod tests {
         evm.create_account(bob, None).unwrap();
         assert!(evm.get_balance(bob).unwrap() == zero);
 
-        //evm.set_balance(bob, one_eth).unwrap();
-        //assert!(evm.get_balance(bob).unwrap() == one_eth);
+        evm.set_balance(bob, one_eth).unwrap();
+        assert!(evm.get_balance(bob).unwrap() == one_eth);
     }
 
     #[test]
@@ -340,8 +1571,8 @@ mod tests {
         evm.create_account(bob, Some(U256::from(2e18))).unwrap();
         evm.create_account(alice, None).unwrap();
 
-        assert!(evm.transfer(alice, bob, one_eth).is_err()); // alice has nothing to transfer...yet
-        assert!(evm.transfer(bob, alice, one_eth).is_ok());
+        assert!(evm.transfer(alice, bob, one_eth, None).is_err()); // alice has nothing to transfer...yet
+        assert!(evm.transfer(bob, alice, one_eth, None).is_ok());
 
         assert!(evm.get_balance(bob).unwrap() == one_eth);
         assert!(evm.get_balance(alice).unwrap() == one_eth);
@@ -350,6 +1581,1093 @@ mod tests {
         println!("{:?}", s);
     }
 
+    #[test]
+    fn state_cheatcodes() {
+        // PUSH0 SLOAD PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN: return the value in slot 0
+        let runtime = [0x5f, 0x54, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3];
+        let bob = Address::repeat_byte(23);
+        let one_eth = U256::from(1e18);
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(one_eth)).unwrap();
+        evm.set_nonce(bob, 5).unwrap();
+        evm.set_storage_at(bob, U256::ZERO, U256::from(42)).unwrap();
+        evm.set_code(bob, runtime.to_vec().into()).unwrap();
+
+        // nothing is clobbered
+        assert_eq!(one_eth, evm.get_balance(bob).unwrap());
+        assert_eq!(5, evm.get_nonce(bob).unwrap());
+        assert_eq!(&runtime[..], &evm.get_code(bob).unwrap()[..]);
+        assert_eq!(U256::from(42), evm.get_storage_at(bob, U256::ZERO).unwrap());
+
+        evm.set_balance(bob, U256::from(1)).unwrap();
+        assert_eq!(5, evm.get_nonce(bob).unwrap());
+        assert_eq!(U256::from(42), evm.get_storage_at(bob, U256::ZERO).unwrap());
+
+        // the etched code can be called
+        let out = evm
+            .transact_call(bob, vec![], U256::ZERO, None)
+            .unwrap()
+            .result;
+        assert_eq!(U256::from(42), U256::from_be_slice(&out));
+
+        // remove the code
+        evm.set_code(bob, Default::default()).unwrap();
+        assert!(evm.get_code(bob).unwrap().is_empty());
+        assert_eq!(U256::from(1), evm.get_balance(bob).unwrap());
+
+        // unknown accounts
+        let alice = Address::repeat_byte(24);
+        assert_eq!(0, evm.get_nonce(alice).unwrap());
+        assert!(evm.get_code(alice).unwrap().is_empty());
+        assert_eq!(U256::ZERO, evm.get_storage_at(alice, U256::ZERO).unwrap());
+    }
+
+    #[test]
+    fn caller_and_prank() {
+        // return (msg.sender, tx.origin)
+        let runtime = [
+            0x33, 0x5f, 0x52, // CALLER PUSH0 MSTORE
+            0x32, 0x60, 0x20, 0x52, // ORIGIN PUSH1 0x20 MSTORE
+            0x60, 0x40, 0x5f, 0xf3, // PUSH1 0x40 PUSH0 RETURN
+        ];
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        let addr = evm
+            .deploy(bob, init_code(&runtime), U256::ZERO, None)
+            .unwrap();
+
+        let sender_and_origin = |out: &[u8]| {
+            (
+                Address::from_slice(&out[12..32]),
+                Address::from_slice(&out[44..64]),
+            )
+        };
+
+        let out = evm
+            .transact_call(addr, vec![], U256::ZERO, None)
+            .unwrap()
+            .result;
+        assert_eq!((Address::ZERO, Address::ZERO), sender_and_origin(&out));
+
+        let out = evm
+            .transact_call(addr, vec![], U256::ZERO, Some(bob))
+            .unwrap()
+            .result;
+        assert_eq!((bob, bob), sender_and_origin(&out));
+
+        // the prank overrides the caller for all transactions
+        evm.start_prank(alice);
+        assert_eq!(Some(alice), evm.prank());
+        let out = evm
+            .transact_call(addr, vec![], U256::ZERO, Some(bob))
+            .unwrap()
+            .result;
+        assert_eq!((alice, alice), sender_and_origin(&out));
+        let out = evm
+            .transact_commit(bob, addr, vec![], U256::ZERO, None)
+            .unwrap()
+            .result;
+        assert_eq!((alice, alice), sender_and_origin(&out));
+
+        // impersonate a contract
+        evm.start_prank(addr);
+        let out = evm
+            .transact_commit(bob, addr, vec![], U256::ZERO, None)
+            .unwrap()
+            .result;
+        assert_eq!((addr, addr), sender_and_origin(&out));
+
+        evm.stop_prank();
+        assert_eq!(None, evm.prank());
+        let out = evm
+            .transact_commit(bob, addr, vec![], U256::ZERO, None)
+            .unwrap()
+            .result;
+        assert_eq!((bob, bob), sender_and_origin(&out));
+    }
+
+    #[test]
+    fn call_tracer() {
+        let bob = Address::repeat_byte(23);
+        let callee = Address::repeat_byte(1);
+        let reverter = Address::repeat_byte(2);
+        let caller = Address::repeat_byte(3);
+
+        // call `callee` then staticcall `reverter`
+        let runtime = [
+            &[0x60, 0x20, 0x5f, 0x5f, 0x5f, 0x5f, 0x73][..],
+            callee.as_slice(),
+            &[0x5a, 0xf1, 0x50, 0x5f, 0x5f, 0x5f, 0x5f, 0x73],
+            reverter.as_slice(),
+            &[0x5a, 0xfa, 0x50, 0x00],
+        ]
+        .concat();
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        // return 42
+        evm.set_code(
+            callee,
+            vec![0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3].into(),
+        )
+        .unwrap();
+        // revert
+        evm.set_code(reverter, vec![0x5f, 0x5f, 0xfd].into())
+            .unwrap();
+        evm.set_code(caller, runtime.into()).unwrap();
+
+        // disabled by default
+        let result = evm
+            .transact_commit(bob, caller, vec![], U256::ZERO, None)
+            .unwrap();
+        assert!(result.trace.is_none());
+
+        evm.set_call_tracing(true);
+        let trace = evm
+            .transact_commit(bob, caller, vec![1, 2], U256::ZERO, None)
+            .unwrap()
+            .trace
+            .unwrap();
+        assert_eq!(CallKind::Call, trace.kind);
+        assert_eq!((bob, caller), (trace.from, trace.to));
+        assert_eq!(vec![1, 2], trace.input.to_vec());
+        assert!(trace.success);
+        assert!(trace.gas_used > 0);
+        assert_eq!(2, trace.calls.len());
+
+        let first = &trace.calls[0];
+        assert_eq!(CallKind::Call, first.kind);
+        assert_eq!((caller, callee), (first.from, first.to));
+        assert_eq!(U256::from(42), U256::from_be_slice(&first.output));
+        assert!(first.success);
+
+        let second = &trace.calls[1];
+        assert_eq!(CallKind::StaticCall, second.kind);
+        assert_eq!(reverter, second.to);
+        assert!(!second.success);
+        assert_eq!(Some("Revert".to_string()), second.error);
+
+        // simulate is traced too
+        let addr = evm
+            .deploy(bob, init_code(&[0x00]), U256::ZERO, None)
+            .unwrap();
+        let result = evm.simulate(bob, addr, vec![], U256::ZERO, None).unwrap();
+        assert_eq!(addr, result.trace.unwrap().to);
+    }
+
+    #[test]
+    fn gas_profiler() {
+        let bob = Address::repeat_byte(23);
+        let caller = Address::repeat_byte(3);
+        let selector = [0xaa, 0xbb, 0xcc, 0xdd];
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        evm.set_gas_profiling(true);
+
+        // return 42
+        let callee_code = [0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3];
+        let callee = evm
+            .deploy(bob, init_code(&callee_code), U256::ZERO, None)
+            .unwrap();
+
+        // call `callee` with all the gas available
+        let runtime = [
+            &[0x60, 0x20, 0x5f, 0x5f, 0x5f, 0x5f, 0x73][..],
+            callee.as_slice(),
+            &[0x5a, 0xf1, 0x50, 0x00],
+        ]
+        .concat();
+        evm.set_code(caller, runtime.into()).unwrap();
+
+        for _ in 0..2 {
+            evm.transact_commit(bob, caller, selector.to_vec(), U256::ZERO, None)
+                .unwrap();
+        }
+
+        let report = evm.gas_report();
+        assert_eq!(8, report.deployments[&callee].size);
+        assert!(report.deployments[&callee].gas > 0);
+
+        let outer = &report.functions[&(caller, Some(selector.into()))];
+        let inner = &report.functions[&(callee, None)];
+        assert_eq!((2, 2), (outer.calls(), inner.calls()));
+        // the caller includes the gas used by the callee
+        assert!(outer.min() > inner.max());
+
+        let calls = report.opcodes[&OpcodeCategory::Call];
+        assert_eq!(2, calls.count);
+        // gas forwarded to the callee isn't included
+        assert!(calls.gas < 10_000);
+        assert!(report.opcodes[&OpcodeCategory::Memory].gas > 0);
+
+        // disabled
+        evm.set_gas_profiling(false);
+        evm.transact_commit(bob, caller, selector.to_vec(), U256::ZERO, None)
+            .unwrap();
+        assert_eq!(2, evm.gas_report().functions[&(callee, None)].calls());
+
+        evm.reset_gas_report();
+        assert!(evm.gas_report().functions.is_empty());
+    }
+
+    #[test]
+    fn execution_errors() {
+        let bob = Address::repeat_byte(23);
+        let reverter = Address::repeat_byte(1);
+        let invalid = Address::repeat_byte(2);
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        // PUSH4 0xdeadbeef PUSH0 MSTORE PUSH1 0x20 PUSH0 REVERT
+        evm.set_code(
+            reverter,
+            vec![
+                0x63, 0xde, 0xad, 0xbe, 0xef, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xfd,
+            ]
+            .into(),
+        )
+        .unwrap();
+        // INVALID
+        evm.set_code(invalid, vec![0xfe].into()).unwrap();
+
+        let err = evm
+            .transact_commit(bob, reverter, vec![], U256::ZERO, None)
+            .err()
+            .unwrap();
+        match err.downcast_ref::<ExecutionError>() {
+            Some(ExecutionError::Revert { data, gas_used }) => {
+                assert_eq!(32, data.len());
+                assert_eq!([0xde, 0xad, 0xbe, 0xef], data[28..]);
+                assert!(*gas_used > 21_000);
+            }
+            _ => panic!("expected a revert"),
+        }
+        assert!(err.to_string().starts_with("Reverted with no reason"));
+
+        let err = evm
+            .transact_call(invalid, vec![], U256::ZERO, None)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(ExecutionError::Halt {
+                reason: HaltReason::InvalidFEOpcode,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn access_list() {
+        let bob = Address::repeat_byte(23);
+        let contract = Address::repeat_byte(1);
+        let other = Address::repeat_byte(2);
+
+        // PUSH1 1 SLOAD POP PUSH20 other BALANCE POP STOP
+        let runtime = [
+            &[0x60, 0x01, 0x54, 0x50, 0x73][..],
+            other.as_slice(),
+            &[0x31, 0x50, 0x00],
+        ]
+        .concat();
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        evm.set_code(contract, runtime.into()).unwrap();
+
+        let result = evm
+            .create_access_list(bob, contract, vec![], U256::ZERO)
+            .unwrap();
+        // the sender and recipient addresses are excluded, but not the recipient's storage
+        assert_eq!(
+            vec![(contract, vec![U256::from(1)]), (other, vec![])],
+            result.access_list
+        );
+        // 2 addresses (2400 each) and 1 slot (1900) in the list. Saves the cold
+        // access cost of BALANCE (2500) and SLOAD (2000)
+        assert_eq!(result.gas_used_without_access_list + 2200, result.gas_used);
+
+        let opts = TxOptions {
+            access_list: result.access_list,
+            ..Default::default()
+        };
+        let out = evm
+            .transact_commit(bob, contract, vec![], U256::ZERO, Some(opts))
+            .unwrap();
+        assert_eq!(result.gas_used, out.gas_used);
+        assert!(result.error.is_none());
+
+        // PUSH1 1 SLOAD POP COINBASE BALANCE POP PUSH0 PUSH0 REVERT
+        let reverter = Address::repeat_byte(3);
+        evm.set_code(reverter, hex!("600154504131505f5ffd").into())
+            .unwrap();
+        let result = evm
+            .create_access_list(bob, reverter, vec![], U256::ZERO)
+            .unwrap();
+        // the accesses until the revert are returned with the error.  The coinbase is
+        // warm from Shanghai
+        assert_eq!(vec![(reverter, vec![U256::from(1)])], result.access_list);
+        assert!(matches!(result.error, Some(ExecutionError::Revert { .. })));
+        assert!(result.gas_used > 0);
+
+        evm.set_spec_id(SpecId::LONDON);
+        let result = evm
+            .create_access_list(bob, reverter, vec![], U256::ZERO)
+            .unwrap();
+        assert_eq!(
+            vec![(Address::ZERO, vec![]), (reverter, vec![U256::from(1)])],
+            result.access_list
+        );
+    }
+
+    #[test]
+    fn bundles() {
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let reverts = Address::repeat_byte(1);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(100))).unwrap();
+        // PUSH0 PUSH0 REVERT
+        evm.set_code(reverts, vec![0x5f, 0x5f, 0xfd].into())
+            .unwrap();
+
+        let transfer = |to: Address, value: u64| TxRequest {
+            caller: bob,
+            to: Some(to),
+            value: U256::from(value),
+            ..Default::default()
+        };
+        let bundle = vec![
+            transfer(alice, 60),
+            transfer(reverts, 0),
+            transfer(alice, 30),
+        ];
+
+        // nothing is committed if a transaction fails
+        let err = evm.transact_bundle(bundle.clone(), true).err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(ExecutionError::Revert { .. })
+        ));
+        assert_eq!(1, err.downcast_ref::<BundleError>().unwrap().index);
+        assert_eq!(U256::ZERO, evm.get_balance(alice).unwrap());
+        assert_eq!(0, evm.block_gas_used());
+
+        // later transactions see the changes of earlier ones
+        let results = evm.simulate_bundle(bundle.clone(), false).unwrap();
+        assert_eq!(
+            vec![true, false, true],
+            results.iter().map(Result::is_ok).collect::<Vec<_>>()
+        );
+        assert_eq!(U256::ZERO, evm.get_balance(alice).unwrap());
+        assert!(evm
+            .simulate_bundle(vec![transfer(alice, 60), transfer(alice, 60)], false)
+            .unwrap()[1]
+            .is_err());
+
+        let results = evm.transact_bundle(bundle, false).unwrap();
+        assert!(results[1].is_err());
+        assert_eq!(U256::from(90), evm.get_balance(alice).unwrap());
+        assert_eq!(U256::from(10), evm.get_balance(bob).unwrap());
+
+        let results = evm
+            .transact_bundle(vec![transfer(alice, 10)], true)
+            .unwrap();
+        assert_eq!(21_000, results[0].as_ref().unwrap().gas_used);
+        assert_eq!(U256::from(100), evm.get_balance(alice).unwrap());
+    }
+
+    #[test]
+    fn mining() {
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(1e18))).unwrap();
+        evm.set_block_config(BlockConfig {
+            gas_limit: 50_000,
+            basefee: U256::from(1),
+            ..Default::default()
+        });
+        let start = evm.block_config();
+        let transfer = |tip: u64| TxRequest {
+            caller: bob,
+            to: Some(alice),
+            value: U256::from(1),
+            opts: Some(TxOptions {
+                max_priority_fee_per_gas: Some(U256::from(tip)),
+                gas_limit: Some(21_000),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        evm.set_tx_ordering(TxOrdering::PriorityFee);
+        let hashes = [1, 3, 2].map(|tip| evm.send_transaction(transfer(tip)).unwrap());
+        assert_eq!(hashes.to_vec(), evm.pending_transactions());
+        assert_eq!(U256::ZERO, evm.get_balance(alice).unwrap());
+
+        // only 2 transfers fit in the block
+        let (first, dropped) = evm.mine_block(12);
+        assert!(dropped.is_empty());
+        assert_eq!(vec![hashes[1], hashes[2]], first.transactions);
+        assert_eq!(42_000, first.header.gas_used);
+        assert_eq!(50_000, first.header.gas_limit);
+        assert_eq!(B256::ZERO, first.header.parent_hash);
+        assert_eq!(vec![hashes[0]], evm.pending_transactions());
+        assert_eq!(U256::from(2), evm.get_balance(alice).unwrap());
+
+        let (second, _) = evm.mine_block(12);
+        assert_eq!(1, second.transactions.len());
+        assert_eq!(first.header.number + 1, second.header.number);
+        assert_eq!(first.header.timestamp + 12, second.header.timestamp);
+        assert_eq!(first.header.hash(), second.header.parent_hash);
+        assert!(evm.pending_transactions().is_empty());
+
+        // can never fit in a block
+        let mut too_big = transfer(1);
+        too_big.opts.as_mut().unwrap().gas_limit = Some(60_000);
+        assert!(evm.send_transaction(too_big).is_err());
+
+        // a block is mined for each transaction
+        evm.set_mining_mode(MiningMode::Auto(1));
+        evm.send_transaction(transfer(1)).unwrap();
+        evm.transfer(bob, alice, U256::from(1), None).unwrap();
+        let next = evm.mine_block(0).0.header;
+        assert_eq!(second.header.number + 3, next.number);
+        assert_eq!(second.header.timestamp + 14, next.timestamp);
+        assert_eq!(U256::from(5), evm.get_balance(alice).unwrap());
+        assert_eq!(start.basefee, evm.basefee());
+
+        // a transaction that reverts still pays the fee and uses the nonce
+        let reverter = Address::repeat_byte(1);
+        // PUSH0 PUSH0 REVERT
+        evm.set_code(reverter, vec![0x5f, 0x5f, 0xfd].into())
+            .unwrap();
+        evm.set_mining_mode(MiningMode::Manual);
+        let nonce = evm.get_nonce(bob).unwrap();
+        let balance = evm.get_balance(bob).unwrap();
+        let mut revert = transfer(1);
+        revert.to = Some(reverter);
+        revert.opts.as_mut().unwrap().gas_limit = Some(30_000);
+        let hash = evm.send_transaction(revert).unwrap();
+        evm.mine_block(12);
+        let receipt = evm.get_receipt(hash).unwrap().clone();
+        assert!(!receipt.status);
+        assert!(receipt.gas_used > 21_000);
+        assert_eq!(nonce + 1, evm.get_nonce(bob).unwrap());
+        assert_eq!(
+            balance - receipt.effective_gas_price * U256::from(receipt.gas_used),
+            evm.get_balance(bob).unwrap()
+        );
+
+        // invalid transactions are dropped with their error
+        let mut invalid = transfer(1);
+        invalid.opts.as_mut().unwrap().gas_price = Some(U256::from(2));
+        let hash = evm.send_transaction(invalid.clone()).unwrap();
+        let (block, dropped) = evm.mine_block(12);
+        assert!(block.transactions.is_empty());
+        assert_eq!(
+            vec![hash],
+            dropped.iter().map(|tx| tx.hash).collect::<Vec<_>>()
+        );
+        assert!(dropped[0].error.to_string().contains("gas_price"));
+        assert!(evm.get_receipt(hash).is_none());
+        // and returned by `send_transaction` if mined right away
+        evm.set_mining_mode(MiningMode::Auto(12));
+        assert!(evm.send_transaction(invalid).is_err());
+        assert!(evm.pending_transactions().is_empty());
+    }
+
+    #[test]
+    fn interval_mining() {
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(1e18))).unwrap();
+        evm.warp(1_000);
+        let number = evm.block_number();
+
+        evm.set_mining_mode(MiningMode::Interval(10));
+        let transfer = TxRequest {
+            caller: bob,
+            to: Some(alice),
+            value: U256::from(1),
+            ..Default::default()
+        };
+        let hash = evm.send_transaction(transfer.clone()).unwrap();
+        evm.transfer(bob, alice, U256::from(1), None).unwrap();
+        // time only moves with warp
+        evm.warp(1_009);
+        assert_eq!(number, evm.block_number());
+        assert_eq!(vec![hash], evm.pending_transactions());
+
+        evm.warp(1_015);
+        assert_eq!((number + 1, 1_015), (evm.block_number(), evm.timestamp()));
+        let block = evm.get_block(number).unwrap();
+        assert_eq!(2, block.transactions.len());
+        assert_eq!(1_009, block.header.timestamp);
+        assert_eq!(U256::from(2), evm.get_balance(alice).unwrap());
+
+        // the next block starts at the warped timestamp
+        evm.send_transaction(transfer).unwrap();
+        evm.warp(1_024);
+        assert_eq!(number + 1, evm.block_number());
+        evm.warp(1_025);
+        assert_eq!(number + 2, evm.block_number());
+        assert_eq!(1, evm.get_block(number + 1).unwrap().transactions.len());
+    }
+
+    #[test]
+    fn receipts() {
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let logger = Address::repeat_byte(1);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(100))).unwrap();
+        // PUSH1 1 PUSH1 32 PUSH0 LOG1 STOP
+        evm.set_code(
+            logger,
+            vec![0x60, 0x01, 0x60, 0x20, 0x5f, 0xa1, 0x00].into(),
+        )
+        .unwrap();
+
+        let transfer = evm.transfer(bob, alice, U256::from(1), None).unwrap();
+        let first = evm
+            .transact_commit(bob, logger, vec![], U256::ZERO, None)
+            .unwrap();
+        let second = evm
+            .transact_commit(bob, logger, vec![], U256::ZERO, None)
+            .unwrap();
+        // PUSH0 PUSH0 RETURN
+        let deployed = evm
+            .deploy(bob, vec![0x5f, 0x5f, 0xf3], U256::ZERO, None)
+            .unwrap();
+        assert!(evm
+            .simulate(bob, logger, vec![], U256::ZERO, None)
+            .unwrap()
+            .tx_hash
+            .is_none());
+
+        let receipt = evm.get_receipt(transfer.tx_hash.unwrap()).unwrap().clone();
+        assert!(receipt.status);
+        assert_eq!(
+            (0, 21_000, 21_000),
+            (
+                receipt.transaction_index,
+                receipt.gas_used,
+                receipt.cumulative_gas_used
+            )
+        );
+        let tx = evm.get_transaction(receipt.transaction_hash).unwrap();
+        assert_eq!((bob, Some(alice)), (tx.from, tx.to));
+
+        let receipt = evm.get_receipt(second.tx_hash.unwrap()).unwrap();
+        assert_eq!(2, receipt.transaction_index);
+        assert_eq!(
+            21_000 + first.gas_used + second.gas_used,
+            receipt.cumulative_gas_used
+        );
+        assert_eq!(1, receipt.logs[0].log_index);
+        assert_eq!(logger, receipt.logs[0].log.address);
+        assert!(!receipt.logs_bloom.is_zero());
+
+        // the last transaction is the deploy
+        let number = receipt.block_number;
+        assert!(evm.get_block(number).is_none());
+        evm.update_block(12);
+        let block = evm.get_block(number).unwrap();
+        assert_eq!(4, block.transactions.len());
+        let receipt = evm.get_receipt(block.transactions[3]).unwrap();
+        assert_eq!(Some(deployed), receipt.contract_address);
+        assert_eq!(block.header.gas_used, receipt.cumulative_gas_used);
+        assert!(evm
+            .get_transaction(block.transactions[3])
+            .unwrap()
+            .to
+            .is_none());
+    }
+
+    #[test]
+    fn estimate_gas() {
+        let bob = Address::repeat_byte(23);
+        let caller = Address::repeat_byte(1);
+        let callee = Address::repeat_byte(2);
+        let clear = Address::repeat_byte(3);
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        // PUSH1 1 PUSH0 SSTORE STOP
+        evm.set_code(callee, vec![0x60, 0x01, 0x5f, 0x55, 0x00].into())
+            .unwrap();
+        // call `callee` with all the gas available and revert if it fails
+        let runtime = [
+            &[0x5f, 0x5f, 0x5f, 0x5f, 0x5f, 0x73][..],
+            callee.as_slice(),
+            &[
+                0x5a, 0xf1, 0x15, 0x60, 0x21, 0x57, 0x00, 0x5b, 0x5f, 0x5f, 0xfd,
+            ],
+        ]
+        .concat();
+        evm.set_code(caller, runtime.into()).unwrap();
+        // PUSH0 PUSH0 SSTORE STOP: clear a slot for a refund
+        evm.set_code(clear, vec![0x5f, 0x5f, 0x55, 0x00].into())
+            .unwrap();
+        evm.set_storage_at(clear, U256::ZERO, U256::from(1))
+            .unwrap();
+
+        for to in [caller, clear] {
+            let estimate = evm.estimate_gas(bob, to, vec![], U256::ZERO).unwrap();
+            let env = evm
+                .build_env(
+                    Some(bob),
+                    TransactTo::call(to),
+                    Bytes::new(),
+                    U256::ZERO,
+                    None,
+                )
+                .unwrap();
+            let result = evm.call_with_gas_limit(&env, estimate).unwrap();
+            assert!(estimate > result.gas_used);
+            assert!(evm.call_with_gas_limit(&env, estimate - 1).is_err());
+        }
+
+        // a plain transfer
+        assert_eq!(
+            21_000,
+            evm.estimate_gas(bob, Address::ZERO, vec![], U256::ZERO)
+                .unwrap()
+        );
+
+        // a revert is returned
+        evm.set_code(callee, vec![0x5f, 0x5f, 0xfd].into()).unwrap();
+        let err = evm
+            .estimate_gas(bob, callee, vec![], U256::ZERO)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(ExecutionError::Revert { .. })
+        ));
+    }
+
+    #[test]
+    fn gas_limits() {
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let looper = Address::repeat_byte(1);
+        let with_limit = |gas_limit| {
+            Some(TxOptions {
+                gas_limit: Some(gas_limit),
+                ..Default::default()
+            })
+        };
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(1e18))).unwrap();
+        // JUMPDEST PUSH0 JUMP
+        evm.set_code(looper, vec![0x5b, 0x5f, 0x56].into()).unwrap();
+
+        let err = evm
+            .transact_commit(bob, looper, vec![], U256::ZERO, with_limit(30_000))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(ExecutionError::OutOfGas {
+                gas_limit: 30_000,
+                gas_used: 30_000
+            })
+        ));
+
+        // not enough for the intrinsic gas
+        let err = evm
+            .transfer(bob, alice, U256::from(1), with_limit(1_000))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(ExecutionError::OutOfGas {
+                gas_limit: 1_000,
+                gas_used: 0
+            })
+        ));
+
+        // any gas limit is allowed by default
+        evm.set_block_config(BlockConfig {
+            gas_limit: 50_000,
+            ..Default::default()
+        });
+        evm.transfer(bob, alice, U256::from(1), with_limit(100_000))
+            .unwrap();
+
+        evm.set_enforce_block_gas_limit(true);
+        evm.transfer(bob, alice, U256::from(1), with_limit(21_000))
+            .unwrap();
+        let err = evm
+            .transfer(bob, alice, U256::from(1), with_limit(21_000))
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(ExecutionError::GasLimitExceedsBlock {
+                gas_limit: 21_000,
+                available: 8_000
+            })
+        ));
+        // the default is the gas left in the block
+        assert!(evm.transfer(bob, alice, U256::from(1), None).is_err());
+        // reads are not limited by the gas used in the block
+        assert!(evm.transact_call(alice, vec![], U256::ZERO, None).is_ok());
+
+        evm.update_block(12);
+        evm.transfer(bob, alice, U256::from(1), None).unwrap();
+    }
+
+    #[test]
+    fn struct_logs() {
+        // PUSH1 0x2a PUSH0 SSTORE PUSH0 SLOAD PUSH0 MSTORE PUSH1 0x20 PUSH0 RETURN
+        let runtime = [
+            0x60, 0x2a, 0x5f, 0x55, 0x5f, 0x54, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3,
+        ];
+        let bob = Address::repeat_byte(23);
+        let addr = Address::repeat_byte(1);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        evm.set_code(addr, runtime.to_vec().into()).unwrap();
+
+        let trace = evm
+            .debug_trace_call(bob, addr, vec![], U256::ZERO, None, Default::default())
+            .unwrap();
+        assert!(!trace.failed);
+        assert_eq!(format!("{:064x}", 42), trace.return_value);
+        assert_eq!(10, trace.struct_logs.len());
+
+        let logs = &trace.struct_logs;
+        assert_eq!(
+            ("PUSH1", 0, 3, 1),
+            (
+                logs[0].op.as_str(),
+                logs[0].pc,
+                logs[0].gas_cost,
+                logs[0].depth
+            )
+        );
+        assert_eq!(Some(vec!["0x2a".to_string()]), logs[1].stack);
+        assert!(logs[1].memory.is_none());
+
+        let storage = BTreeMap::from([(format!("{:064x}", 0), format!("{:064x}", 42))]);
+        assert_eq!("SSTORE", logs[2].op);
+        assert_eq!(22_100, logs[2].gas_cost);
+        assert_eq!(Some(storage.clone()), logs[2].storage);
+        assert_eq!("SLOAD", logs[4].op);
+        assert_eq!(100, logs[4].gas_cost);
+        assert_eq!(Some(storage), logs[4].storage);
+        assert!(logs[3].storage.is_none());
+
+        // nothing was committed
+        assert_eq!(U256::ZERO, evm.get_storage_at(addr, U256::ZERO).unwrap());
+
+        // geth's json format
+        let json = serde_json::to_value(&trace).unwrap();
+        assert_eq!(json["structLogs"][2]["gasCost"], 22_100);
+        assert!(json["structLogs"][0].get("memory").is_none());
+
+        // memory and a failed transaction
+        evm.set_code(
+            addr,
+            vec![0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xfd].into(),
+        )
+        .unwrap();
+        let config = StructLoggerConfig {
+            enable_memory: true,
+            disable_stack: true,
+            ..Default::default()
+        };
+        let trace = evm
+            .debug_trace_call(bob, addr, vec![], U256::ZERO, None, config)
+            .unwrap();
+        assert!(trace.failed);
+        assert_eq!(
+            Some(vec![format!("{:064x}", 42)]),
+            trace.struct_logs[5].memory
+        );
+        assert!(trace.struct_logs[5].stack.is_none());
+    }
+
+    #[test]
+    fn checkpoint_and_revert() {
+        let one_eth = U256::from(1e18);
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(3e18))).unwrap();
+
+        let first = evm.checkpoint();
+        let mined = evm.transfer(bob, alice, one_eth, None).unwrap();
+        evm.update_block(12);
+        let number = evm
+            .get_receipt(mined.tx_hash.unwrap())
+            .unwrap()
+            .block_number;
+        assert!(evm.get_block(number).is_some());
+
+        let second = evm.checkpoint();
+        let pending = evm.transfer(bob, alice, one_eth, None).unwrap();
+        assert_eq!(evm.get_balance(alice).unwrap(), U256::from(2e18));
+        assert!(evm.get_receipt(pending.tx_hash.unwrap()).is_some());
+
+        assert!(evm.revert_to(second));
+        assert_eq!(evm.get_balance(alice).unwrap(), one_eth);
+        assert!(evm.get_receipt(pending.tx_hash.unwrap()).is_none());
+        assert!(evm.get_receipt(mined.tx_hash.unwrap()).is_some());
+        // a checkpoint can only be used once
+        assert!(!evm.revert_to(second));
+
+        let third = evm.checkpoint();
+        assert!(evm.revert_to(first));
+        assert_eq!(evm.get_balance(alice).unwrap(), U256::ZERO);
+        assert_eq!(evm.get_balance(bob).unwrap(), U256::from(3e18));
+        // later checkpoints are removed on revert
+        assert!(!evm.discard(third));
+        // so are the receipts and blocks
+        assert!(evm.get_receipt(mined.tx_hash.unwrap()).is_none());
+        assert!(evm.get_block(number).is_none());
+
+        // the block number is mined again
+        let again = evm.transfer(bob, alice, one_eth, None).unwrap();
+        evm.update_block(12);
+        let block = evm.get_block(number).unwrap();
+        assert_eq!(vec![again.tx_hash.unwrap()], block.transactions);
+
+        let fourth = evm.checkpoint();
+        assert!(evm.discard(fourth));
+        assert!(!evm.revert_to(fourth));
+    }
+
+    #[test]
+    fn checkpoint_reverts_storage_and_code() {
+        let bob = Address::repeat_byte(23);
+        let carol = Address::repeat_byte(25);
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        evm.set_storage_at(bob, U256::from(1), U256::from(10))
+            .unwrap();
+
+        let first = evm.checkpoint();
+        evm.set_storage_at(bob, U256::from(1), U256::from(11))
+            .unwrap();
+        evm.set_storage_at(bob, U256::from(2), U256::from(20))
+            .unwrap();
+
+        let second = evm.checkpoint();
+        evm.set_code(carol, Bytes::from_static(&[0x00])).unwrap();
+        evm.set_storage_at(bob, U256::from(1), U256::from(12))
+            .unwrap();
+
+        assert!(evm.revert_to(second));
+        assert!(evm.get_code(carol).unwrap().is_empty());
+        assert_eq!(
+            evm.get_storage_at(bob, U256::from(1)).unwrap(),
+            U256::from(11)
+        );
+
+        assert!(evm.revert_to(first));
+        assert_eq!(
+            evm.get_storage_at(bob, U256::from(1)).unwrap(),
+            U256::from(10)
+        );
+        assert_eq!(evm.get_storage_at(bob, U256::from(2)).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn select_hardfork() {
+        // PUSH0 PUSH0 RETURN: PUSH0 is only valid from Shanghai on
+        let push0 = vec![0x5f, 0x5f, 0xf3];
+        let bob = Address::repeat_byte(23);
+
+        let mut merge = BaseEvm::new_with_spec(None, Some(SpecId::MERGE));
+        merge.create_account(bob, None).unwrap();
+        assert!(merge.deploy(bob, push0.clone(), U256::ZERO, None).is_err());
+
+        let mut shanghai = BaseEvm::new_with_spec(None, Some(SpecId::SHANGHAI));
+        shanghai.create_account(bob, None).unwrap();
+        assert!(shanghai.deploy(bob, push0, U256::ZERO, None).is_ok());
+
+        assert_eq!(SpecId::LATEST, BaseEvm::default().spec_id());
+
+        // the hardfork is saved in the snapshot
+        let snap = shanghai.create_snapshot().unwrap();
+        assert_eq!(Some("shanghai".into()), snap.spec);
+        assert_eq!(SpecId::SHANGHAI, BaseEvm::new_from_snapshot(snap).spec_id());
+    }
+
+    #[test]
+    fn block_config_in_env() {
+        // return (coinbase, chainid, prevrandao, gaslimit, basefee)
+        let runtime = [
+            0x41, 0x5f, 0x52, // COINBASE PUSH0 MSTORE
+            0x46, 0x60, 0x20, 0x52, // CHAINID PUSH1 0x20 MSTORE
+            0x44, 0x60, 0x40, 0x52, // PREVRANDAO PUSH1 0x40 MSTORE
+            0x45, 0x60, 0x60, 0x52, // GASLIMIT PUSH1 0x60 MSTORE
+            0x48, 0x60, 0x80, 0x52, // BASEFEE PUSH1 0x80 MSTORE
+            0x60, 0xa0, 0x5f, 0xf3, // PUSH1 0xa0 PUSH0 RETURN
+        ];
+        let bob = Address::repeat_byte(23);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+
+        assert_eq!(BlockConfig::default(), evm.block_config());
+
+        let config = BlockConfig {
+            chain_id: 5,
+            coinbase: Address::repeat_byte(9),
+            gas_limit: 20_000_000,
+            basefee: U256::from(7e9),
+            prevrandao: B256::repeat_byte(3),
+            difficulty: U256::ZERO,
+        };
+        evm.set_block_config(config.clone());
+        assert_eq!(config, evm.block_config());
+
+        let addr = evm
+            .deploy(bob, init_code(&runtime), U256::ZERO, None)
+            .unwrap();
+        let out = evm
+            .transact_call(addr, vec![], U256::ZERO, None)
+            .unwrap()
+            .result;
+
+        assert_eq!(&out[12..32], Address::repeat_byte(9).as_slice());
+        assert_eq!(U256::from(5), U256::from_be_slice(&out[32..64]));
+        assert_eq!(&out[64..96], B256::repeat_byte(3).as_slice());
+        assert_eq!(U256::from(20_000_000), U256::from_be_slice(&out[96..128]));
+        assert_eq!(U256::from(7e9), U256::from_be_slice(&out[128..160]));
+
+        // saved in the snapshot
+        let snap = evm.create_snapshot().unwrap();
+        assert_eq!(config, BaseEvm::new_from_snapshot(snap).block_config());
+    }
+
+    #[test]
+    fn eip1559_fees() {
+        let gwei = U256::from(1e9);
+        let one_eth = U256::from(1e18);
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let coinbase = Address::repeat_byte(9);
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(10e18))).unwrap();
+        evm.create_account(alice, None).unwrap();
+        evm.set_block_config(BlockConfig {
+            coinbase,
+            basefee: U256::from(10) * gwei,
+            ..Default::default()
+        });
+
+        let opts = TxOptions {
+            max_fee_per_gas: Some(U256::from(30) * gwei),
+            max_priority_fee_per_gas: Some(U256::from(2) * gwei),
+            ..Default::default()
+        };
+        let result = evm.transfer(bob, alice, one_eth, Some(opts)).unwrap();
+        let fee = U256::from(21_000) * U256::from(12) * gwei;
+        assert_eq!(21_000, result.gas_used);
+        assert_eq!(U256::from(12) * gwei, result.effective_gas_price);
+        assert_eq!(fee, result.fee);
+
+        assert_eq!(one_eth, evm.get_balance(alice).unwrap());
+        assert_eq!(
+            U256::from(10e18) - one_eth - fee,
+            evm.get_balance(bob).unwrap()
+        );
+        // only the priority fee goes to the coinbase. the basefee is burned
+        assert_eq!(
+            U256::from(21_000) * U256::from(2) * gwei,
+            evm.get_balance(coinbase).unwrap()
+        );
+
+        // legacy gas price below the basefee
+        let low = TxOptions {
+            gas_price: Some(gwei),
+            ..Default::default()
+        };
+        assert!(evm.transfer(bob, alice, one_eth, Some(low)).is_err());
+
+        // can't mix legacy and EIP-1559 pricing
+        let mixed = TxOptions {
+            gas_price: Some(U256::from(20) * gwei),
+            max_fee_per_gas: Some(U256::from(20) * gwei),
+            ..Default::default()
+        };
+        assert!(evm.transfer(bob, alice, one_eth, Some(mixed)).is_err());
+
+        // no gas price is free
+        let result = evm.transfer(bob, alice, one_eth, None).unwrap();
+        assert_eq!(U256::ZERO, result.fee);
+    }
+
+    #[test]
+    fn dynamic_base_fee() {
+        let gwei = U256::from(1e9);
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(10e18))).unwrap();
+        evm.set_block_config(BlockConfig {
+            gas_limit: 42_000,
+            basefee: gwei,
+            ..Default::default()
+        });
+        let opts = TxOptions {
+            max_fee_per_gas: Some(U256::from(2) * gwei),
+            ..Default::default()
+        };
+
+        // disabled by default. gas is tracked, but the basefee doesn't change
+        evm.transfer(bob, alice, U256::from(1), Some(opts.clone()))
+            .unwrap();
+        assert_eq!(21_000, evm.block_gas_used());
+        evm.update_block(12);
+        assert_eq!(0, evm.block_gas_used());
+        assert_eq!(gwei, evm.basefee());
+
+        evm.set_fee_market(Some(FeeMarket::default()));
+        assert_eq!(Some(FeeMarket::default()), evm.fee_market());
+
+        // at the target (gas_limit / 2)
+        evm.transfer(bob, alice, U256::from(1), Some(opts.clone()))
+            .unwrap();
+        evm.update_block(12);
+        assert_eq!(gwei, evm.basefee());
+
+        // full block
+        let cp = evm.checkpoint();
+        evm.transfer(bob, alice, U256::from(1), Some(opts.clone()))
+            .unwrap();
+        evm.transfer(bob, alice, U256::from(1), Some(opts.clone()))
+            .unwrap();
+        assert_eq!(42_000, evm.block_gas_used());
+        evm.update_block(12);
+        assert_eq!(U256::from(1.125e9), evm.basefee());
+        assert_eq!(U256::from(1.125e9), evm.block_config().basefee);
+
+        // basefee is restored by a checkpoint
+        evm.revert_to(cp);
+        assert_eq!(gwei, evm.basefee());
+
+        // empty block
+        evm.update_block(12);
+        assert_eq!(U256::from(0.875e9), evm.basefee());
+    }
+
     #[test]
     fn no_sol_test_contract() {
         let contract_bytecode = hex::decode(BYTECODE).expect("failed to decode bytecode");
@@ -369,12 +2687,12 @@ mod tests {
         test_contract_abi.bytecode = Some(contract_bytecode.into());
 
         let (args, _) = test_contract_abi.encode_constructor("(1)").unwrap();
-        let contract_address = evm.deploy(owner, args, U256::from(0)).unwrap();
+        let contract_address = evm.deploy(owner, args, U256::from(0), None).unwrap();
 
         // Check owner call
         let (enc_owner_call, _, de1) = test_contract_abi.encode_function("owner", "()").unwrap();
         let o1 = evm
-            .transact_call(contract_address, enc_owner_call, zero)
+            .transact_call(contract_address, enc_owner_call, zero, None)
             .unwrap();
         assert!(DynSolValue::Address(owner) == de1.unwrap().abi_decode(&o1.result).unwrap());
 
@@ -383,7 +2701,7 @@ mod tests {
             .encode_function("increment", "()")
             .unwrap();
         let o2 = evm
-            .transact_commit(owner, contract_address, enc_inc_0, zero)
+            .transact_commit(owner, contract_address, enc_inc_0, zero, None)
             .unwrap();
         assert!(
             DynSolValue::Uint(U256::from(1), 256) == de2.unwrap().abi_decode(&o2.result).unwrap()
@@ -392,7 +2710,7 @@ mod tests {
         // check the value
         let (enc_value_call, _, de3) = test_contract_abi.encode_function("value", "()").unwrap();
         let o3 = evm
-            .transact_call(contract_address, enc_value_call, zero)
+            .transact_call(contract_address, enc_value_call, zero, None)
             .unwrap();
         assert!(
             DynSolValue::Uint(U256::from(2), 256) == de3.unwrap().abi_decode(&o3.result).unwrap()
@@ -403,7 +2721,7 @@ mod tests {
             .encode_function("increment", "(2)")
             .unwrap();
         let o4 = evm
-            .transact_commit(owner, contract_address, enc_inc_1, zero)
+            .transact_commit(owner, contract_address, enc_inc_1, zero, None)
             .unwrap();
         assert!(
             DynSolValue::Tuple(vec![
@@ -417,7 +2735,7 @@ mod tests {
             .encode_function("increment", "()")
             .unwrap();
         let os = evm
-            .simulate(owner, contract_address, enc_inc_sim, zero)
+            .simulate(owner, contract_address, enc_inc_sim, zero, None)
             .unwrap();
         assert!(
             DynSolValue::Uint(U256::from(4), 256) == des.unwrap().abi_decode(&os.result).unwrap()
@@ -426,10 +2744,397 @@ mod tests {
         // make sure value didn't change from 'simulate'
         let (enc_value_call1, _, de5) = test_contract_abi.encode_function("value", "()").unwrap();
         let o5 = evm
-            .transact_call(contract_address, enc_value_call1, zero)
+            .transact_call(contract_address, enc_value_call1, zero, None)
             .unwrap();
         assert!(
             DynSolValue::Uint(U256::from(4), 256) == de5.unwrap().abi_decode(&o5.result).unwrap()
         );
     }
+
+    #[test]
+    fn console_logs() {
+        let bob = Address::repeat_byte(23);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        assert!(!evm.console_logs());
+        evm.set_console_logs(true, false);
+        let mut data = keccak256("log(string,uint256)")[..4].to_vec();
+        data.extend(
+            DynSolValue::Tuple(vec![
+                DynSolValue::String("count:".into()),
+                DynSolValue::Uint(U256::from(3), 256),
+            ])
+            .abi_encode_params(),
+        );
+
+        let result = evm
+            .transact_commit(bob, CONSOLE_ADDRESS, data.clone(), U256::ZERO, None)
+            .unwrap();
+        assert_eq!(vec!["count: 3".to_string()], result.console_logs);
+        let result = evm
+            .simulate(bob, CONSOLE_ADDRESS, data.clone(), U256::ZERO, None)
+            .unwrap();
+        assert_eq!(vec!["count: 3".to_string()], result.console_logs);
+
+        evm.set_console_logs(false, true);
+        let result = evm
+            .transact_commit(bob, CONSOLE_ADDRESS, data, U256::ZERO, None)
+            .unwrap();
+        assert!(result.console_logs.is_empty());
+    }
+
+    #[test]
+    fn custom_precompiles() {
+        let reverse = Address::repeat_byte(9);
+        let sha256 = Address::with_last_byte(2);
+        let mut evm = BaseEvm::default();
+        evm.register_precompile(reverse, |input: &Bytes, gas_limit: u64| {
+            let gas_used = 100 + input.len() as u64;
+            if gas_used > gas_limit {
+                return Err(PrecompileError::OutOfGas.into());
+            }
+            match input.first() {
+                None => return Err(PrecompileError::other("no input").into()),
+                Some(0) => {
+                    return Err(CustomPrecompileError::Revert {
+                        output: Bytes::from_static(&[4]),
+                        gas_used,
+                    })
+                }
+                _ => {}
+            }
+            let output = input.iter().rev().copied().collect::<Vec<_>>();
+            Ok((gas_used, output.into()))
+        });
+        // replaces the standard precompile
+        evm.register_precompile(
+            sha256,
+            StubPrecompile {
+                output: Bytes::from_static(&[7]),
+                gas_used: 10,
+            },
+        );
+
+        let result = evm
+            .transact_call(reverse, vec![1, 2, 3], U256::ZERO, None)
+            .unwrap();
+        assert_eq!(vec![3, 2, 1], result.result);
+        let stub = evm
+            .transact_call(sha256, vec![1, 2, 3], U256::ZERO, None)
+            .unwrap();
+        assert_eq!(vec![7], stub.result);
+        assert_eq!(result.gas_used - 103, stub.gas_used - 10);
+
+        // also used when inspecting
+        evm.set_call_tracing(true);
+        let err = evm
+            .transact_call(reverse, vec![], U256::ZERO, None)
+            .err()
+            .unwrap();
+        assert!(matches!(
+            err.downcast_ref::<ExecutionError>(),
+            Some(ExecutionError::Halt {
+                reason: HaltReason::PrecompileError,
+                ..
+            })
+        ));
+
+        // unlike a halt, a revert doesn't use all the gas
+        let err = evm
+            .transact_call(reverse, vec![0], U256::ZERO, None)
+            .err()
+            .unwrap();
+        let Some(ExecutionError::Revert { data, gas_used }) = err.downcast_ref::<ExecutionError>()
+        else {
+            panic!("expected a revert: {err}")
+        };
+        assert_eq!(vec![4], data.to_vec());
+        // intrinsic gas, 1 zero byte of calldata and the precompile
+        assert_eq!(21_000 + 4 + 101, *gas_used);
+
+        assert!(evm.remove_precompile(reverse));
+        assert!(!evm.remove_precompile(reverse));
+        let result = evm
+            .transact_call(reverse, vec![1, 2, 3], U256::ZERO, None)
+            .unwrap();
+        assert!(result.result.is_empty());
+    }
+
+    #[test]
+    fn coverage() {
+        let bob = Address::repeat_byte(23);
+        let contract = Address::repeat_byte(24);
+        // PUSH1 1 PUSH1 7 JUMPI STOP STOP JUMPDEST STOP
+        let code = Bytes::from_static(&[0x60, 1, 0x60, 7, 0x57, 0, 0, 0x5b, 0]);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        evm.set_code(contract, code.clone()).unwrap();
+        let sources = [ContractSources {
+            bytecode: code,
+            source_map: "0:5:0:-;;6:5;18:5;18:5;12:5;".into(),
+            sources: [(0, ("a.sol".into(), "line1\nline2\nline3\nline4\n".into()))].into(),
+        }];
+
+        // not enabled
+        evm.transact_commit(bob, contract, vec![], U256::ZERO, None)
+            .unwrap();
+        let report = evm.coverage_lcov(&sources).unwrap();
+        assert!(report.contains("LH:0\n"));
+        assert!(report.contains("BRDA:2,4,0,-\n"));
+
+        evm.set_coverage(true);
+        evm.transact_commit(bob, contract, vec![], U256::ZERO, None)
+            .unwrap();
+        evm.simulate(bob, contract, vec![], U256::ZERO, None)
+            .unwrap();
+        assert_eq!(
+            "TN:\nSF:a.sol\nDA:1,2\nDA:2,2\nDA:3,2\nDA:4,0\nLF:4\nLH:3\n\
+             BRDA:2,4,0,2\nBRDA:2,4,1,0\nBRF:2\nBRH:1\nend_of_record\n",
+            evm.coverage_lcov(&sources).unwrap()
+        );
+
+        evm.reset_coverage();
+        let report = evm.coverage_lcov(&sources).unwrap();
+        assert!(report.contains("LH:0\n"));
+    }
+
+    #[test]
+    fn coverage_of_deployed_contracts() {
+        let bob = Address::repeat_byte(23);
+        // constructors that return the runtime code STOP, and PUSH0 STOP
+        let first = hex!("600060005360016000f3").to_vec();
+        let second = hex!("615f006000526002601ef3").to_vec();
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, None).unwrap();
+        evm.set_coverage(true);
+
+        let first = evm.deploy(bob, first, U256::ZERO, None).unwrap();
+        let second = evm.deploy(bob, second, U256::ZERO, None).unwrap();
+        for address in [first, second] {
+            evm.transact_commit(bob, address, vec![], U256::ZERO, None)
+                .unwrap();
+        }
+
+        // the constructors don't share a bytecode hash
+        assert!(!evm.coverage_map.bytecodes.contains_key(&B256::ZERO));
+        let pcs = |code: &[u8]| {
+            evm.coverage_map.bytecodes[&keccak256(code)]
+                .pcs
+                .clone()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![(0, 1)], pcs(&hex!("00")));
+        assert_eq!(vec![(0, 1), (1, 1)], pcs(&hex!("5f00")));
+        assert_eq!(2, evm.coverage_map.bytecodes.len());
+    }
+
+    #[test]
+    fn create2_deploy() {
+        let bob = Address::repeat_byte(23);
+        let salt = B256::with_last_byte(1);
+        // returns the runtime code PUSH1 42 PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let runtime = hex!("602a60005260206000f3");
+        let init_code = hex!("69602a60005260206000f3600052600a6016f3").to_vec();
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(1e18))).unwrap();
+        assert!(!evm.get_code(CREATE2_FACTORY).unwrap().is_empty());
+
+        let address = evm
+            .deploy_create2(bob, salt, init_code.clone(), U256::ZERO, None)
+            .unwrap();
+        assert_eq!(create2_address(CREATE2_FACTORY, salt, &init_code), address);
+        assert_eq!(runtime, evm.get_code(address).unwrap().as_ref());
+        let result = evm
+            .transact_call(address, vec![], U256::ZERO, None)
+            .unwrap();
+        assert_eq!(U256::from(42), U256::from_be_slice(&result.result));
+
+        // the address is taken
+        assert!(evm
+            .deploy_create2(bob, salt, init_code.clone(), U256::ZERO, None)
+            .is_err());
+
+        // the init code returns no runtime code
+        let err = evm
+            .deploy_create2(bob, salt, vec![0x00], U256::ZERO, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("no code"));
+
+        let nonce = evm.get_nonce(bob).unwrap();
+        let address = evm.deploy(bob, init_code, U256::ZERO, None).unwrap();
+        assert_eq!(create_address(bob, nonce), address);
+    }
+
+    #[test]
+    fn deploy_at() {
+        let bob = Address::repeat_byte(23);
+        let target = Address::repeat_byte(42);
+        // constructor: SSTORE(0, CALLVALUE), copy the runtime code to memory, MSTORE(1, ADDRESS)
+        // to set the immutable, and return the runtime code
+        // runtime: PUSH32 <immutable> PUSH1 0 MSTORE PUSH1 0 SLOAD PUSH1 32 MSTORE
+        //          PUSH1 64 PUSH1 0 RETURN
+        let runtime = |immutable: B256| {
+            [
+                [0x7f].as_slice(),
+                immutable.as_slice(),
+                &hex!("60005260005460205260406000f3"),
+            ]
+            .concat()
+        };
+        let init_code = [
+            hex!("34600055" "602f6014600039" "30600152" "602f6000f3").as_slice(),
+            &runtime(B256::ZERO),
+        ]
+        .concat();
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(10))).unwrap();
+
+        evm.deploy_at(bob, target, init_code.clone(), U256::from(5))
+            .unwrap();
+        assert_eq!(
+            runtime(target.into_word()),
+            evm.get_code(target).unwrap().to_vec()
+        );
+        let result = evm.transact_call(target, vec![], U256::ZERO, None).unwrap();
+        assert_eq!(target.into_word(), B256::from_slice(&result.result[..32]));
+        assert_eq!(U256::from(5), U256::from_be_slice(&result.result[32..]));
+        // the value is paid by the caller
+        assert_eq!(U256::from(5), evm.get_balance(target).unwrap());
+        assert_eq!(U256::from(5), evm.get_balance(bob).unwrap());
+        assert_eq!(
+            (0, 1),
+            (evm.get_nonce(bob).unwrap(), evm.get_nonce(target).unwrap())
+        );
+        assert!(evm.get_receipt(B256::ZERO).is_none());
+
+        assert!(evm
+            .deploy_at(bob, target, init_code.clone(), U256::ZERO)
+            .is_err());
+        // a constructor that fails leaves no code
+        let other = Address::repeat_byte(43);
+        assert!(evm
+            .deploy_at(bob, other, init_code, U256::from(100))
+            .is_err());
+        assert!(evm.get_code(other).unwrap().is_empty());
+        // so does one that returns no runtime code
+        assert!(evm
+            .deploy_at(bob, other, hex!("00").to_vec(), U256::ZERO)
+            .is_err());
+        assert!(evm.get_code(other).unwrap().is_empty());
+        assert_eq!(0, evm.get_nonce(other).unwrap());
+
+        // contracts created by the constructor have the same addresses as if it ran
+        // after CREATE: CREATE(0, 0, 0), SSTORE(0, <address>) and return 1 byte
+        evm.deploy_at(
+            bob,
+            other,
+            hex!("600060006000f060005560016000f3").to_vec(),
+            U256::ZERO,
+        )
+        .unwrap();
+        assert_eq!(
+            create_address(other, 1).into_word(),
+            B256::from(evm.get_storage_at(other, U256::ZERO).unwrap())
+        );
+        assert_eq!(2, evm.get_nonce(other).unwrap());
+    }
+
+    #[test]
+    fn revert_roll() {
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(1e18))).unwrap();
+
+        evm.roll(100);
+        let hash = evm
+            .transfer(bob, alice, U256::from(1), None)
+            .unwrap()
+            .tx_hash
+            .unwrap();
+        let checkpoint = evm.checkpoint();
+        evm.roll(200);
+        evm.update_block(12);
+        assert_eq!(200, evm.get_receipt(hash).unwrap().block_number);
+
+        assert!(evm.revert_to(checkpoint));
+        assert_eq!(100, evm.block_number());
+        assert_eq!(100, evm.get_receipt(hash).unwrap().block_number);
+        assert_eq!(100, evm.get_transaction(hash).unwrap().block_number);
+        evm.update_block(12);
+        assert_eq!(vec![hash], evm.get_block(100).unwrap().transactions);
+    }
+
+    #[test]
+    fn warp_roll_and_batch_mining() {
+        let bob = Address::repeat_byte(23);
+        let alice = Address::repeat_byte(24);
+        let mut evm = BaseEvm::default();
+        evm.create_account(bob, Some(U256::from(1e18))).unwrap();
+
+        evm.warp(1_000);
+        evm.roll(100);
+        assert_eq!((100, 1_000), (evm.block_number(), evm.timestamp()));
+        // block.number and block.timestamp of a contract
+        // NUMBER PUSH1 0 MSTORE TIMESTAMP PUSH1 32 MSTORE PUSH1 64 PUSH1 0 RETURN
+        let contract = Address::repeat_byte(25);
+        evm.set_code(contract, hex!("436000524260205260406000f3").into())
+            .unwrap();
+        let result = evm
+            .transact_call(contract, vec![], U256::ZERO, None)
+            .unwrap();
+        assert_eq!(U256::from(100), U256::from_be_slice(&result.result[..32]));
+        assert_eq!(U256::from(1_000), U256::from_be_slice(&result.result[32..]));
+
+        // the committed transfer moves to the new block number
+        let hash = evm
+            .transfer(bob, alice, U256::from(1), None)
+            .unwrap()
+            .tx_hash
+            .unwrap();
+        evm.roll(200);
+        assert_eq!(200, evm.get_receipt(hash).unwrap().block_number);
+        assert!(evm.get_block(100).is_none());
+        evm.update_block(12);
+        assert_eq!(vec![hash], evm.get_block(200).unwrap().transactions);
+
+        // going back reuses block numbers
+        evm.roll(200);
+        evm.update_block(12);
+        assert!(evm.get_block(200).unwrap().transactions.is_empty());
+        assert_eq!(200, evm.get_receipt(hash).unwrap().block_number);
+        evm.roll(200);
+        evm.warp(500);
+        assert_eq!((200, 500), (evm.block_number(), evm.timestamp()));
+
+        // a block every 2 transactions
+        evm.set_mining_mode(MiningMode::Batch {
+            transactions: 2,
+            block_time: 10,
+        });
+        evm.transfer(bob, alice, U256::from(1), None).unwrap();
+        assert_eq!(200, evm.block_number());
+        evm.transfer(bob, alice, U256::from(1), None).unwrap();
+        assert_eq!((201, 510), (evm.block_number(), evm.timestamp()));
+        assert_eq!(2, evm.get_block(200).unwrap().transactions.len());
+        evm.send_transaction(TxRequest {
+            caller: bob,
+            to: Some(alice),
+            value: U256::from(1),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(201, evm.block_number());
+        evm.transfer(bob, alice, U256::from(1), None).unwrap();
+        evm.send_transaction(TxRequest {
+            caller: bob,
+            to: Some(alice),
+            value: U256::from(1),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(202, evm.block_number());
+        assert_eq!(U256::from(6), evm.get_balance(alice).unwrap());
+    }
 }
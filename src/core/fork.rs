@@ -1,6 +1,7 @@
 //
 
 use crate::core::{
+    block::BlockConfig,
     errors::DatabaseError,
     fork_backend::ForkBackend,
     snapshot::{SnapShot, SnapShotAccountRecord, SnapShotSource},
@@ -16,6 +17,7 @@ pub struct Fork {
     pub db: CacheDB<ForkBackend>,
     pub block_number: u64,
     pub timestamp: u64,
+    pub block_config: BlockConfig,
 }
 
 impl Fork {
@@ -23,10 +25,12 @@ impl Fork {
         let backend = ForkBackend::new(url, starting_block_number);
         let block_number = backend.block_number;
         let timestamp = backend.timestamp;
+        let block_config = backend.block_config.clone();
         Self {
             db: CacheDB::new(backend),
             block_number,
             timestamp,
+            block_config,
         }
     }
 
@@ -67,6 +71,8 @@ impl Fork {
         Ok(SnapShot {
             block_num,
             timestamp,
+            spec: None,
+            block: None,
             source: SnapShotSource::Fork,
             accounts,
         })
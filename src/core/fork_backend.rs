@@ -1,15 +1,15 @@
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{Address, B256, U256};
 use anyhow::Result;
 use ethers_core::types::{Block, BlockId, BlockNumber, TxHash, H160, H256, U64};
 use ethers_providers::{Http, Middleware, Provider, ProviderError};
 use revm::{
-    primitives::{AccountInfo, Bytecode, B256, KECCAK_EMPTY},
+    primitives::{AccountInfo, Bytecode, KECCAK_EMPTY},
     DatabaseRef,
 };
 use std::sync::Arc;
 use tokio::runtime::{Builder, Handle, RuntimeFlavor};
 
-use crate::core::errors::DatabaseError;
+use crate::core::{block::BlockConfig, errors::DatabaseError};
 
 pub type HttpProvider = Provider<Http>;
 
@@ -18,6 +18,7 @@ pub struct ForkBackend {
     provider: Arc<HttpProvider>,
     pub block_number: u64,
     pub timestamp: u64,
+    pub block_config: BlockConfig,
 }
 
 impl ForkBackend {
@@ -42,6 +43,21 @@ impl ForkBackend {
             .expect("ForkBackend: Got 'pending' block number")
             .as_u64();
         let timestamp = blk.timestamp.as_u64();
+
+        let chain_id = Self::block_on(provider.get_chainid())
+            .expect("ForkBackend: failed to load the chain id")
+            .as_u64();
+        let block_config = BlockConfig {
+            chain_id,
+            coinbase: blk.author.map(|a| Address::from(a.0)).unwrap_or_default(),
+            gas_limit: blk.gas_limit.as_u64(),
+            basefee: blk
+                .base_fee_per_gas
+                .map(|b| U256::from_limbs(b.0))
+                .unwrap_or_default(),
+            prevrandao: blk.mix_hash.map(|h| B256::new(h.0)).unwrap_or_default(),
+            difficulty: U256::from_limbs(blk.difficulty.0),
+        };
         /*
         let block_number = if let Some(bn) = starting_block_number {
             bn
@@ -56,6 +72,7 @@ impl ForkBackend {
             provider,
             block_number,
             timestamp,
+            block_config,
         }
     }
 
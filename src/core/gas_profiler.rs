@@ -0,0 +1,353 @@
+//!
+//! Gas profiler.  Attribute the gas used by transactions to each call frame,
+//! function selector and category of opcode.  Reports from many transactions
+//! can be merged to produce something similar to `forge test --gas-report`
+//!
+use alloy_primitives::{Address, FixedBytes};
+use revm::{
+    interpreter::{
+        opcode, CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, Interpreter,
+    },
+    Database, EvmContext, Inspector,
+};
+use std::collections::BTreeMap;
+
+/// The first 4 bytes of calldata
+pub type Selector = FixedBytes<4>;
+
+/// Groups of opcodes in the gas report
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OpcodeCategory {
+    Sload,
+    Sstore,
+    /// CALL, CALLCODE, DELEGATECALL, STATICCALL, CREATE and CREATE2. Gas
+    /// forwarded to the new call frame is not included
+    Call,
+    /// LOG0 - LOG4
+    Log,
+    /// opcodes that read or write memory: MLOAD, MSTORE, MSTORE8, MSIZE,
+    /// MCOPY and the *COPY opcodes
+    Memory,
+    Other,
+}
+
+impl OpcodeCategory {
+    /// The category of `op`
+    pub fn from_opcode(op: u8) -> Self {
+        match op {
+            opcode::SLOAD => OpcodeCategory::Sload,
+            opcode::SSTORE => OpcodeCategory::Sstore,
+            opcode::CALL
+            | opcode::CALLCODE
+            | opcode::DELEGATECALL
+            | opcode::STATICCALL
+            | opcode::CREATE
+            | opcode::CREATE2 => OpcodeCategory::Call,
+            opcode::LOG0..=opcode::LOG4 => OpcodeCategory::Log,
+            opcode::MLOAD
+            | opcode::MSTORE
+            | opcode::MSTORE8
+            | opcode::MSIZE
+            | opcode::MCOPY
+            | opcode::CALLDATACOPY
+            | opcode::CODECOPY
+            | opcode::EXTCODECOPY
+            | opcode::RETURNDATACOPY => OpcodeCategory::Memory,
+            _ => OpcodeCategory::Other,
+        }
+    }
+
+    /// The name used in the report
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OpcodeCategory::Sload => "SLOAD",
+            OpcodeCategory::Sstore => "SSTORE",
+            OpcodeCategory::Call => "CALL",
+            OpcodeCategory::Log => "LOG",
+            OpcodeCategory::Memory => "MEMORY",
+            OpcodeCategory::Other => "OTHER",
+        }
+    }
+}
+
+/// The gas used by each call to a function.  Calls are counted by the amount of gas
+/// used, so the median is exact and memory only grows with the number of distinct amounts
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GasStats {
+    // number of calls by gas used
+    histogram: BTreeMap<u64, u64>,
+    calls: u64,
+    total: u64,
+}
+
+impl GasStats {
+    /// Record the gas used by a call
+    pub fn record(&mut self, gas: u64) {
+        *self.histogram.entry(gas).or_default() += 1;
+        self.calls += 1;
+        self.total += gas;
+    }
+
+    /// Number of calls
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    pub fn min(&self) -> u64 {
+        self.histogram.keys().next().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> u64 {
+        self.histogram
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn avg(&self) -> u64 {
+        match self.calls {
+            0 => 0,
+            n => self.total / n,
+        }
+    }
+
+    pub fn median(&self) -> u64 {
+        match self.calls {
+            0 => 0,
+            n if n % 2 == 0 => (self.nth(n / 2 - 1) + self.nth(n / 2)) / 2,
+            n => self.nth(n / 2),
+        }
+    }
+
+    // The gas used by the `n`th call, in increasing order of gas
+    fn nth(&self, n: u64) -> u64 {
+        let mut seen = 0;
+        for (gas, count) in &self.histogram {
+            seen += count;
+            if seen > n {
+                return *gas;
+            }
+        }
+        0
+    }
+
+    fn merge(&mut self, other: GasStats) {
+        for (gas, count) in other.histogram {
+            *self.histogram.entry(gas).or_default() += count;
+        }
+        self.calls += other.calls;
+        self.total += other.total;
+    }
+}
+
+/// Number of times a category of opcodes was executed and the gas used
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OpcodeGas {
+    pub count: u64,
+    pub gas: u64,
+}
+
+/// The cost of deploying a contract
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeploymentGas {
+    /// gas used by the init code
+    pub gas: u64,
+    /// size of the deployed code
+    pub size: usize,
+}
+
+/// Gas used, aggregated across transactions.
+///
+/// Gas is the execution gas of a call frame, including nested calls. It does not
+/// include the intrinsic gas of the transaction or refunds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GasReport {
+    /// Calls by (address of the code, selector).  The selector is None if the
+    /// calldata is less than 4 bytes, e.g. `receive` or `fallback`
+    pub functions: BTreeMap<(Address, Option<Selector>), GasStats>,
+    /// Contracts created by address
+    pub deployments: BTreeMap<Address, DeploymentGas>,
+    pub opcodes: BTreeMap<OpcodeCategory, OpcodeGas>,
+}
+
+impl GasReport {
+    /// Add the results of `other` to this report
+    pub fn merge(&mut self, other: GasReport) {
+        for (key, stats) in other.functions {
+            self.functions.entry(key).or_default().merge(stats);
+        }
+        self.deployments.extend(other.deployments);
+        for (category, used) in other.opcodes {
+            let entry = self.opcodes.entry(category).or_default();
+            entry.count += used.count;
+            entry.gas += used.gas;
+        }
+    }
+}
+
+/// An `Inspector` that builds a `GasReport`
+#[derive(Debug, Default)]
+pub struct GasProfiler {
+    report: GasReport,
+    // opcode and gas remaining at the start of the current step
+    opcode: u8,
+    gas_remaining: u64,
+}
+
+impl GasProfiler {
+    /// Return the report
+    pub fn into_report(self) -> GasReport {
+        self.report
+    }
+
+    // Remove the gas forwarded to a new call frame from the cost of the
+    // CALL/CREATE opcode that started it
+    fn forwarded(&mut self, gas: u64) {
+        if let Some(used) = self.report.opcodes.get_mut(&OpcodeCategory::Call) {
+            used.gas = used.gas.saturating_sub(gas);
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for GasProfiler {
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        self.opcode = interp.current_opcode();
+        self.gas_remaining = interp.gas.remaining();
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let used = self
+            .report
+            .opcodes
+            .entry(OpcodeCategory::from_opcode(self.opcode))
+            .or_default();
+        used.count += 1;
+        used.gas += self.gas_remaining.saturating_sub(interp.gas.remaining());
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        // depth is 0 for the transaction itself, which isn't started by an opcode
+        if context.journaled_state.depth() > 0 {
+            // the stipend is added to the gas forwarded, not charged to the caller
+            let stipend = match inputs.context.scheme {
+                CallScheme::Call | CallScheme::CallCode if !inputs.transfer.value.is_zero() => {
+                    revm::interpreter::gas::CALL_STIPEND
+                }
+                _ => 0,
+            };
+            self.forwarded(inputs.gas_limit.saturating_sub(stipend));
+        }
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        let selector = inputs
+            .input
+            .get(..4)
+            .map(|bytes| Selector::from_slice(bytes));
+        self.report
+            .functions
+            .entry((inputs.context.code_address, selector))
+            .or_default()
+            .record(outcome.result.gas.spent());
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        if context.journaled_state.depth() > 0 {
+            self.forwarded(inputs.gas_limit);
+        }
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        if let (Some(address), true) = (outcome.address, outcome.result.result.is_ok()) {
+            self.report.deployments.insert(
+                address,
+                DeploymentGas {
+                    gas: outcome.result.gas.spent(),
+                    size: outcome.result.output.len(),
+                },
+            );
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gas_stats() {
+        let mut stats = GasStats::default();
+        assert_eq!(
+            (0, 0, 0, 0),
+            (stats.min(), stats.avg(), stats.median(), stats.max())
+        );
+
+        for gas in [30, 10, 20, 100] {
+            stats.record(gas);
+        }
+        assert_eq!(4, stats.calls());
+        assert_eq!(
+            (10, 40, 25, 100),
+            (stats.min(), stats.avg(), stats.median(), stats.max())
+        );
+
+        let mut other = GasStats::default();
+        other.record(5);
+        stats.merge(other);
+        assert_eq!((5, 20), (stats.min(), stats.median()));
+        assert_eq!(165, stats.total());
+
+        // repeated amounts are counted, not stored
+        stats.record(20);
+        stats.record(20);
+        assert_eq!((7, 20, 100), (stats.calls(), stats.median(), stats.max()));
+        assert_eq!(5, stats.histogram.len());
+    }
+
+    #[test]
+    fn opcode_categories() {
+        assert_eq!(
+            OpcodeCategory::Log,
+            OpcodeCategory::from_opcode(opcode::LOG2)
+        );
+        assert_eq!(
+            OpcodeCategory::Call,
+            OpcodeCategory::from_opcode(opcode::CREATE2)
+        );
+        assert_eq!(
+            OpcodeCategory::Memory,
+            OpcodeCategory::from_opcode(opcode::MSTORE)
+        );
+        assert_eq!(
+            OpcodeCategory::Other,
+            OpcodeCategory::from_opcode(opcode::ADD)
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//!
+//! Select the hardfork (`SpecId`) used by the EVM
+//!
+use anyhow::{bail, Result};
+use revm::primitives::SpecId;
+
+/// Mainnet blocks for forks activated by block number. Ordered newest first.
+const MAINNET_BLOCK_FORKS: [(u64, SpecId); 15] = [
+    (15_537_394, SpecId::MERGE),
+    (15_050_000, SpecId::GRAY_GLACIER),
+    (13_773_000, SpecId::ARROW_GLACIER),
+    (12_965_000, SpecId::LONDON),
+    (12_244_000, SpecId::BERLIN),
+    (9_200_000, SpecId::MUIR_GLACIER),
+    (9_069_000, SpecId::ISTANBUL),
+    (7_280_000, SpecId::PETERSBURG),
+    (4_370_000, SpecId::BYZANTIUM),
+    (2_675_000, SpecId::SPURIOUS_DRAGON),
+    (2_463_000, SpecId::TANGERINE),
+    (1_920_000, SpecId::DAO_FORK),
+    (1_150_000, SpecId::HOMESTEAD),
+    (200_000, SpecId::FRONTIER_THAWING),
+    (0, SpecId::FRONTIER),
+];
+
+/// Mainnet timestamps for forks activated by time (post-merge). Ordered newest first.
+const MAINNET_TIMESTAMP_FORKS: [(u64, SpecId); 2] = [
+    (1_710_338_135, SpecId::CANCUN),
+    (1_681_338_455, SpecId::SHANGHAI),
+];
+
+/// Parse the name of a hardfork into a `SpecId`.  The name is case-insensitive
+/// and ignores spaces, dashes, and underscores. For example: `"cancun"`,
+/// `"Shanghai"`, `"gray_glacier"`, or `"latest"`.
+pub fn parse_spec_id(name: &str) -> Result<SpecId> {
+    let normalized = name
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .collect::<String>()
+        .to_lowercase();
+
+    let spec = match normalized.as_str() {
+        "frontier" => SpecId::FRONTIER,
+        "frontierthawing" => SpecId::FRONTIER_THAWING,
+        "homestead" => SpecId::HOMESTEAD,
+        "daofork" => SpecId::DAO_FORK,
+        "tangerine" | "tangerinewhistle" => SpecId::TANGERINE,
+        "spurious" | "spuriousdragon" => SpecId::SPURIOUS_DRAGON,
+        "byzantium" => SpecId::BYZANTIUM,
+        "constantinople" => SpecId::CONSTANTINOPLE,
+        "petersburg" => SpecId::PETERSBURG,
+        "istanbul" => SpecId::ISTANBUL,
+        "muirglacier" => SpecId::MUIR_GLACIER,
+        "berlin" => SpecId::BERLIN,
+        "london" => SpecId::LONDON,
+        "arrowglacier" => SpecId::ARROW_GLACIER,
+        "grayglacier" => SpecId::GRAY_GLACIER,
+        "merge" | "paris" => SpecId::MERGE,
+        "shanghai" => SpecId::SHANGHAI,
+        "cancun" => SpecId::CANCUN,
+        "latest" => SpecId::LATEST,
+        _ => bail!("unknown hardfork: '{}'", name),
+    };
+    Ok(spec)
+}
+
+/// Return the name of the hardfork.  The result can be parsed with `parse_spec_id`.
+pub fn spec_id_name(spec: SpecId) -> &'static str {
+    match spec {
+        SpecId::FRONTIER => "frontier",
+        SpecId::FRONTIER_THAWING => "frontier_thawing",
+        SpecId::HOMESTEAD => "homestead",
+        SpecId::DAO_FORK => "dao_fork",
+        SpecId::TANGERINE => "tangerine",
+        SpecId::SPURIOUS_DRAGON => "spurious_dragon",
+        SpecId::BYZANTIUM => "byzantium",
+        SpecId::CONSTANTINOPLE => "constantinople",
+        SpecId::PETERSBURG => "petersburg",
+        SpecId::ISTANBUL => "istanbul",
+        SpecId::MUIR_GLACIER => "muir_glacier",
+        SpecId::BERLIN => "berlin",
+        SpecId::LONDON => "london",
+        SpecId::ARROW_GLACIER => "arrow_glacier",
+        SpecId::GRAY_GLACIER => "gray_glacier",
+        SpecId::MERGE => "merge",
+        SpecId::SHANGHAI => "shanghai",
+        SpecId::CANCUN => "cancun",
+        SpecId::LATEST => "latest",
+    }
+}
+
+/// Return the hardfork active on Ethereum mainnet at the given block number and timestamp.
+pub fn mainnet_spec_id(block_number: u64, timestamp: u64) -> SpecId {
+    if block_number >= MAINNET_BLOCK_FORKS[0].0 {
+        if let Some((_, spec)) = MAINNET_TIMESTAMP_FORKS
+            .iter()
+            .find(|(activation, _)| timestamp >= *activation)
+        {
+            return *spec;
+        }
+    }
+
+    MAINNET_BLOCK_FORKS
+        .iter()
+        .find(|(activation, _)| block_number >= *activation)
+        .map(|(_, spec)| *spec)
+        .unwrap_or(SpecId::FRONTIER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_name_hardforks() {
+        assert_eq!(SpecId::CANCUN, parse_spec_id("Cancun").unwrap());
+        assert_eq!(SpecId::GRAY_GLACIER, parse_spec_id("Gray Glacier").unwrap());
+        assert_eq!(SpecId::MERGE, parse_spec_id("paris").unwrap());
+        assert!(parse_spec_id("bob").is_err());
+
+        let spec = SpecId::SPURIOUS_DRAGON;
+        assert_eq!(spec, parse_spec_id(spec_id_name(spec)).unwrap());
+    }
+
+    #[test]
+    fn mainnet_schedule() {
+        assert_eq!(SpecId::FRONTIER, mainnet_spec_id(1, 1438269988));
+        assert_eq!(SpecId::LONDON, mainnet_spec_id(12_965_000, 1628166822));
+        assert_eq!(SpecId::MERGE, mainnet_spec_id(15_537_394, 1663224179));
+        assert_eq!(SpecId::SHANGHAI, mainnet_spec_id(17_034_870, 1681338479));
+        assert_eq!(SpecId::CANCUN, mainnet_spec_id(19_426_587, 1710338135));
+    }
+}
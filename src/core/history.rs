@@ -0,0 +1,315 @@
+//!
+//! Committed transactions, their receipts and the blocks they are in.  Similar to
+//! `eth_getTransactionByHash`, `eth_getTransactionReceipt` and `eth_getBlockByNumber`
+//!
+use alloy_primitives::{Address, Bloom, Bytes, Log, B256, U256};
+use revm::primitives::{TransactTo, TxEnv};
+use std::collections::HashMap;
+
+use crate::core::{
+    block::BlockHeader,
+    logs::{IndexedLog, LogFilter, LogMark, LogStore},
+};
+
+/// A committed transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Transaction {
+    pub hash: B256,
+    pub from: Address,
+    /// None if the transaction created a contract
+    pub to: Option<Address>,
+    pub value: U256,
+    pub input: Bytes,
+    pub gas_limit: u64,
+    pub block_number: u64,
+    /// position of the transaction in the block
+    pub transaction_index: u64,
+}
+
+impl Transaction {
+    /// Create the transaction from the environment it was executed in.  The index
+    /// is set by `History::record`
+    pub fn from_env(hash: B256, tx: &TxEnv, block_number: u64) -> Self {
+        Self {
+            hash,
+            from: tx.caller,
+            to: match tx.transact_to {
+                TransactTo::Call(to) => Some(to),
+                TransactTo::Create(_) => None,
+            },
+            value: tx.value,
+            input: tx.data.clone(),
+            gas_limit: tx.gas_limit,
+            block_number,
+            transaction_index: 0,
+        }
+    }
+}
+
+/// A log emitted by a transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceiptLog {
+    pub log: Log,
+    /// position of the log in the block
+    pub log_index: u64,
+}
+
+/// The outcome of a committed transaction
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Receipt {
+    pub transaction_hash: B256,
+    pub block_number: u64,
+    pub transaction_index: u64,
+    /// false if the transaction failed.  Only transactions mined from the mempool
+    /// can fail, see `BaseEvm::mine_block`
+    pub status: bool,
+    pub gas_used: u64,
+    /// gas used by this and the previous transactions in the block
+    pub cumulative_gas_used: u64,
+    pub effective_gas_price: U256,
+    /// the address of the contract created, if any
+    pub contract_address: Option<Address>,
+    pub logs: Vec<ReceiptLog>,
+    pub logs_bloom: Bloom,
+}
+
+impl Receipt {
+    /// Create a receipt for the transaction.  The indices are set by `History::record`
+    pub fn new(
+        tx: &Transaction,
+        status: bool,
+        gas_used: u64,
+        cumulative_gas_used: u64,
+        effective_gas_price: U256,
+        contract_address: Option<Address>,
+        logs: Vec<Log>,
+    ) -> Self {
+        let mut logs_bloom = Bloom::default();
+        logs.iter().for_each(|log| logs_bloom.accrue_log(log));
+        Self {
+            transaction_hash: tx.hash,
+            block_number: tx.block_number,
+            transaction_index: 0,
+            status,
+            gas_used,
+            cumulative_gas_used,
+            effective_gas_price,
+            contract_address,
+            logs: logs
+                .into_iter()
+                .map(|log| ReceiptLog { log, log_index: 0 })
+                .collect(),
+            logs_bloom,
+        }
+    }
+}
+
+/// A mined block and the hashes of its transactions, in order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<B256>,
+}
+
+/// Every committed transaction and mined block
+#[derive(Debug, Default)]
+pub struct History {
+    // transactions in the order they were committed
+    transactions: Vec<(Transaction, Receipt)>,
+    by_hash: HashMap<B256, usize>,
+    // blocks in the order they were mined
+    blocks: Vec<Block>,
+    // positions in `blocks` by block number.  A number is reused if the block
+    // number is set back, see `BaseEvm::roll`
+    by_number: HashMap<u64, Vec<usize>>,
+    // position in `transactions` of the first transaction of the current block,
+    // and the number of logs in the current block
+    current: usize,
+    current_logs: u64,
+    logs: LogStore,
+}
+
+/// A position in the history to roll back to.  See `History::mark`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryMark {
+    transactions: usize,
+    blocks: usize,
+    current: usize,
+    current_logs: u64,
+    logs: LogMark,
+    // block number of the transactions in the current block, which `roll` may change
+    block_number: Option<u64>,
+}
+
+impl History {
+    /// Add a transaction to the current block
+    pub fn record(&mut self, mut tx: Transaction, mut receipt: Receipt) {
+        tx.transaction_index = self.current_transactions() as u64;
+        receipt.transaction_index = tx.transaction_index;
+        for (i, log) in receipt.logs.iter_mut().enumerate() {
+            log.log_index = self.current_logs;
+            self.current_logs += 1;
+            self.logs.push(log.log.address, self.transactions.len(), i);
+        }
+        self.by_hash.insert(tx.hash, self.transactions.len());
+        self.transactions.push((tx, receipt));
+    }
+
+    /// The number of transactions in the current block
+    pub fn current_transactions(&self) -> usize {
+        self.transactions.len() - self.current
+    }
+
+    /// The current position in the history.  See `rollback`
+    pub fn mark(&self) -> HistoryMark {
+        HistoryMark {
+            transactions: self.transactions.len(),
+            blocks: self.blocks.len(),
+            current: self.current,
+            current_logs: self.current_logs,
+            logs: self.logs.mark(),
+            block_number: self
+                .transactions
+                .get(self.current)
+                .map(|(tx, _)| tx.block_number),
+        }
+    }
+
+    /// Remove the transactions and blocks added since `mark`.  The transactions left in
+    /// the current block get back their block number
+    pub fn rollback(&mut self, mark: HistoryMark) {
+        for (tx, _) in self
+            .transactions
+            .drain(mark.transactions.min(self.transactions.len())..)
+        {
+            self.by_hash.remove(&tx.hash);
+        }
+        self.logs.rollback(mark.logs);
+        for block in self.blocks.drain(mark.blocks.min(self.blocks.len())..) {
+            if let Some(positions) = self.by_number.get_mut(&block.header.number) {
+                positions.pop();
+                if positions.is_empty() {
+                    self.by_number.remove(&block.header.number);
+                }
+            }
+        }
+        self.current = mark.current;
+        self.current_logs = mark.current_logs;
+        if let Some(number) = mark.block_number {
+            self.set_current_block_number(number);
+        }
+    }
+
+    /// Set the block number of the transactions in the current block.  See `BaseEvm::roll`
+    pub fn set_current_block_number(&mut self, number: u64) {
+        for (tx, receipt) in self.transactions[self.current..].iter_mut() {
+            tx.block_number = number;
+            receipt.block_number = number;
+        }
+    }
+
+    /// Close the current block, returning a copy of it
+    pub fn seal(&mut self, header: BlockHeader) -> Block {
+        let block = Block {
+            transactions: self.transactions[self.current..]
+                .iter()
+                .map(|(tx, _)| tx.hash)
+                .collect(),
+            header,
+        };
+        self.by_number
+            .entry(block.header.number)
+            .or_default()
+            .push(self.blocks.len());
+        self.blocks.push(block.clone());
+        self.logs.seal(block.header.number);
+        self.current = self.transactions.len();
+        self.current_logs = 0;
+        block
+    }
+
+    /// The most recent block mined
+    pub fn latest_block(&self) -> Option<&Block> {
+        self.blocks.last()
+    }
+
+    /// The block with the given number.  The most recent one, if the number was reused
+    pub fn block(&self, number: u64) -> Option<&Block> {
+        let position = self.by_number.get(&number)?.last()?;
+        self.blocks.get(*position)
+    }
+
+    pub fn transaction(&self, hash: &B256) -> Option<&Transaction> {
+        let position = self.by_hash.get(hash)?;
+        self.transactions.get(*position).map(|(tx, _)| tx)
+    }
+
+    pub fn receipt(&self, hash: &B256) -> Option<&Receipt> {
+        let position = self.by_hash.get(hash)?;
+        self.transactions.get(*position).map(|(_, receipt)| receipt)
+    }
+
+    /// Logs emitted by the committed transactions that match the `filter`
+    pub fn logs(&self, filter: &LogFilter) -> Vec<IndexedLog> {
+        self.logs
+            .query(filter, |position| &self.transactions[position].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{BloomInput, LogData};
+
+    #[test]
+    fn record_and_rollback() {
+        let log = Log {
+            address: Address::repeat_byte(1),
+            data: LogData::new_unchecked(vec![B256::repeat_byte(2)], Bytes::new()),
+        };
+        let mut history = History::default();
+        let record = |history: &mut History, byte: u8, logs: usize| {
+            let tx = Transaction::from_env(B256::repeat_byte(byte), &TxEnv::default(), 1);
+            let receipt = Receipt::new(&tx, true, 1, 1, U256::ZERO, None, vec![log.clone(); logs]);
+            history.record(tx, receipt);
+        };
+
+        record(&mut history, 1, 2);
+        let mark = history.mark();
+        record(&mut history, 2, 1);
+        history.rollback(mark);
+        assert!(history.receipt(&B256::repeat_byte(2)).is_none());
+        record(&mut history, 3, 1);
+
+        let receipt = history.receipt(&B256::repeat_byte(3)).unwrap();
+        assert_eq!(1, receipt.transaction_index);
+        assert_eq!(2, receipt.logs[0].log_index);
+        let logs = history.logs(&LogFilter::default());
+        assert_eq!(
+            vec![(0, 0), (0, 1), (1, 2)],
+            logs.iter()
+                .map(|log| (log.transaction_index, log.log_index))
+                .collect::<Vec<_>>()
+        );
+        assert!(receipt
+            .logs_bloom
+            .contains_input(BloomInput::Raw(log.address.as_slice())));
+
+        let header = BlockHeader {
+            number: 1,
+            timestamp: 0,
+            gas_used: 2,
+            gas_limit: 10,
+            base_fee: U256::ZERO,
+            parent_hash: B256::ZERO,
+        };
+        history.seal(header.clone());
+        let block = history.block(1).unwrap();
+        assert_eq!(header, block.header);
+        assert_eq!(
+            vec![B256::repeat_byte(1), B256::repeat_byte(3)],
+            block.transactions
+        );
+        assert_eq!(Some(block), history.latest_block());
+    }
+}
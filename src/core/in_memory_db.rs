@@ -15,7 +15,7 @@ use revm::{
 
 ///
 /// This acts like a wrapper type for [InMemoryDB] but is capable of creating/applying snapshots
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct MemDb {
     pub db: CacheDB<EmptyDBWrapper>,
 }
@@ -58,6 +58,8 @@ impl MemDb {
         Ok(SnapShot {
             block_num,
             timestamp,
+            spec: None,
+            block: None,
             source: SnapShotSource::Memory,
             accounts,
         })
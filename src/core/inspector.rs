@@ -0,0 +1,218 @@
+//!
+//! A collection of optional `Inspector`s used while executing a transaction.
+//!
+//! Adapted from Foundry's `InspectorStack`.  Each inspector is only called
+//! if it's enabled.
+//!
+use alloy_primitives::{Address, Log, U256};
+use revm::{
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter},
+    Database, EvmContext, Inspector,
+};
+
+use crate::core::{
+    access_list::AccessListTracer, call_tracer::CallTracer, console::ConsoleLogger,
+    coverage::CoverageCollector, gas_profiler::GasProfiler, struct_logger::StructLogger,
+};
+
+/// Calls each enabled inspector in turn
+#[derive(Debug, Default)]
+pub struct InspectorStack {
+    pub access_list: Option<AccessListTracer>,
+    pub call_tracer: Option<CallTracer>,
+    pub struct_logger: Option<StructLogger>,
+    pub gas_profiler: Option<GasProfiler>,
+    pub console_logger: Option<ConsoleLogger>,
+    pub coverage: Option<CoverageCollector>,
+}
+
+impl InspectorStack {
+    /// Is at least one inspector enabled?  If not, the transaction can be
+    /// run without the overhead of inspection.
+    pub fn is_empty(&self) -> bool {
+        self.access_list.is_none()
+            && self.call_tracer.is_none()
+            && self.struct_logger.is_none()
+            && self.gas_profiler.is_none()
+            && self.console_logger.is_none()
+            && self.coverage.is_none()
+    }
+}
+
+/// Call the method on each of the enabled inspectors
+macro_rules! call_inspectors {
+    ($id:ident, [ $($inspector:expr),+ ], $call:block) => {
+        $(
+            if let Some($id) = $inspector $call
+        )+
+    };
+}
+
+impl<DB: Database> Inspector<DB> for InspectorStack {
+    fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            { inspector.initialize_interp(interp, context) }
+        );
+    }
+
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            { inspector.step(interp, context) }
+        );
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            { inspector.step_end(interp, context) }
+        );
+    }
+
+    fn log(&mut self, context: &mut EvmContext<DB>, log: &Log) {
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            { inspector.log(context, log) }
+        );
+    }
+
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            {
+                if let Some(outcome) = inspector.call(context, inputs) {
+                    return Some(outcome);
+                }
+            }
+        );
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        let mut outcome = outcome;
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            { outcome = inspector.call_end(context, inputs, outcome) }
+        );
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            {
+                if let Some(outcome) = inspector.create(context, inputs) {
+                    return Some(outcome);
+                }
+            }
+        );
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        let mut outcome = outcome;
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            { outcome = inspector.create_end(context, inputs, outcome) }
+        );
+        outcome
+    }
+
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        call_inspectors!(
+            inspector,
+            [
+                &mut self.access_list,
+                &mut self.call_tracer,
+                &mut self.struct_logger,
+                &mut self.gas_profiler,
+                &mut self.console_logger,
+                &mut self.coverage
+            ],
+            { Inspector::<DB>::selfdestruct(inspector, contract, target, value) }
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//!
+//! Index of every log emitted by committed transactions.  Similar to `eth_getLogs`
+//!
+use alloy_dyn_abi::DynSolValue;
+use alloy_primitives::{Address, Log, B256};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::core::{abi::ContractAbi, history::Receipt};
+
+/// A log and where it was emitted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexedLog {
+    pub log: Log,
+    pub block_number: u64,
+    pub transaction_hash: B256,
+    pub transaction_index: u64,
+    /// position of the log in the block
+    pub log_index: u64,
+}
+
+impl IndexedLog {
+    /// Decode the log with the events in the `abi`, returning the name of the event
+    /// and its values.  None if the log isn't an event in the ABI
+    pub fn decode(&self, abi: &ContractAbi) -> Option<(String, DynSolValue)> {
+        abi.extract_logs(vec![self.log.clone()]).pop()
+    }
+}
+
+/// Select logs by where they were emitted and their topics
+#[derive(Clone, Debug, Default)]
+pub struct LogFilter {
+    /// first block to include. Default: the first block
+    pub from_block: Option<u64>,
+    /// last block to include. Default: the current block
+    pub to_block: Option<u64>,
+    /// contracts that emitted the log. Empty matches any contract
+    pub addresses: Vec<Address>,
+    /// topics by position. `None` matches any topic, otherwise the topic
+    /// must be one of the values
+    pub topics: Vec<Option<Vec<B256>>>,
+}
+
+impl LogFilter {
+    fn matches_topics(&self, log: &Log) -> bool {
+        let topics = log.topics();
+        self.topics
+            .iter()
+            .enumerate()
+            .all(|(i, expected)| match expected {
+                Some(values) if !values.is_empty() => {
+                    topics.get(i).is_some_and(|topic| values.contains(topic))
+                }
+                _ => true,
+            })
+    }
+}
+
+/// Where the logs of committed transactions are, in the order they were emitted,
+/// indexed by address and block number.  The logs themselves are kept in the receipts
+#[derive(Debug, Default)]
+pub struct LogStore {
+    logs: Vec<LogRef>,
+    // positions in `logs` of the logs emitted by each address
+    by_address: HashMap<Address, Vec<usize>>,
+    // positions in `logs` of the logs in each mined block.  The number is the one the
+    // block was mined with: `BaseEvm::roll` only renumbers the current block
+    by_block: BTreeMap<u64, Vec<usize>>,
+    // number of logs in mined blocks. The logs after them are in the current block
+    sealed: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct LogRef {
+    address: Address,
+    // position of the receipt, and of the log in the receipt
+    receipt: usize,
+    log: usize,
+    // number of the block the log was mined in. None if it's in the current block
+    block: Option<u64>,
+}
+
+/// A position in the store to roll back to.  See `LogStore::mark`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogMark {
+    logs: usize,
+    sealed: usize,
+}
+
+impl LogStore {
+    /// Add the log at position `log` in the receipt at position `receipt` to the
+    /// current block
+    pub fn push(&mut self, address: Address, receipt: usize, log: usize) {
+        self.by_address
+            .entry(address)
+            .or_default()
+            .push(self.logs.len());
+        self.logs.push(LogRef {
+            address,
+            receipt,
+            log,
+            block: None,
+        });
+    }
+
+    /// Move the logs of the current block to block `number`
+    pub fn seal(&mut self, number: u64) {
+        let positions = self.by_block.entry(number).or_default();
+        for (i, log) in self.logs.iter_mut().enumerate().skip(self.sealed) {
+            log.block = Some(number);
+            positions.push(i);
+        }
+        self.sealed = self.logs.len();
+    }
+
+    /// The current position in the store.  See `rollback`
+    pub fn mark(&self) -> LogMark {
+        LogMark {
+            logs: self.logs.len(),
+            sealed: self.sealed,
+        }
+    }
+
+    /// Remove the logs added since `mark`, and move the logs of blocks mined since
+    /// back to the current block
+    pub fn rollback(&mut self, mark: LogMark) {
+        let len = mark.logs.min(self.logs.len());
+        for log in self.logs.split_off(len).into_iter().rev() {
+            if let Some(positions) = self.by_address.get_mut(&log.address) {
+                positions.pop();
+            }
+            self.unseal(log.block);
+        }
+        for i in (mark.sealed..self.sealed.min(len)).rev() {
+            let block = self.logs[i].block.take();
+            self.unseal(block);
+        }
+        self.sealed = mark.sealed.min(len);
+    }
+
+    // Remove the last log of the mined block from the block index
+    fn unseal(&mut self, block: Option<u64>) {
+        let Some(number) = block else {
+            return;
+        };
+        if let Some(positions) = self.by_block.get_mut(&number) {
+            positions.pop();
+            if positions.is_empty() {
+                self.by_block.remove(&number);
+            }
+        }
+    }
+
+    /// Return the logs that match the `filter`, in the order they were emitted.
+    /// `receipt` returns the receipt at a position given to `push`
+    pub fn query<'a>(
+        &self,
+        filter: &LogFilter,
+        receipt: impl Fn(usize) -> &'a Receipt,
+    ) -> Vec<IndexedLog> {
+        let from = filter.from_block.unwrap_or(0);
+        let to = filter.to_block.unwrap_or(u64::MAX);
+        if from > to {
+            return vec![];
+        }
+        let in_range = |number: u64| (from..=to).contains(&number);
+        // all the logs of the current block have its number
+        let current = self
+            .logs
+            .get(self.sealed)
+            .map(|log| receipt(log.receipt).block_number);
+
+        let positions = if filter.addresses.is_empty() {
+            let mut positions = self
+                .by_block
+                .range(from..=to)
+                .flat_map(|(_, positions)| positions)
+                .copied()
+                .collect::<Vec<_>>();
+            // block numbers may be set back, so logs aren't necessarily in block order
+            positions.sort_unstable();
+            if current.is_some_and(in_range) {
+                positions.extend(self.sealed..self.logs.len());
+            }
+            positions
+        } else {
+            let mut positions = filter
+                .addresses
+                .iter()
+                .filter_map(|address| self.by_address.get(address))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            positions.sort_unstable();
+            positions.dedup();
+            positions.retain(|i| self.logs[*i].block.or(current).is_some_and(in_range));
+            positions
+        };
+
+        positions
+            .into_iter()
+            .filter_map(|i| {
+                let LogRef {
+                    receipt: r, log, ..
+                } = self.logs[i];
+                let receipt = receipt(r);
+                let log = &receipt.logs[log];
+                filter.matches_topics(&log.log).then(|| IndexedLog {
+                    log: log.log.clone(),
+                    block_number: receipt.block_number,
+                    transaction_hash: receipt.transaction_hash,
+                    transaction_index: receipt.transaction_index,
+                    log_index: log.log_index,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::history::Transaction;
+    use alloy_primitives::{Bytes, LogData, U256};
+    use revm::primitives::TxEnv;
+
+    #[test]
+    fn query() {
+        let receipt = |block_number: u64, address: u8, topics: &[u8]| {
+            let log = Log {
+                address: Address::repeat_byte(address),
+                data: LogData::new_unchecked(
+                    topics.iter().map(|t| B256::repeat_byte(*t)).collect(),
+                    Bytes::new(),
+                ),
+            };
+            let tx = Transaction::from_env(B256::ZERO, &TxEnv::default(), block_number);
+            Receipt::new(&tx, true, 0, 0, U256::ZERO, None, vec![log])
+        };
+        let receipts = [
+            receipt(1, 1, &[1, 2]),
+            receipt(1, 2, &[1, 3]),
+            receipt(2, 1, &[1, 3]),
+            receipt(3, 1, &[2]),
+        ];
+        let mut store = LogStore::default();
+        let mut mark = store.mark();
+        for (i, receipt) in receipts.iter().enumerate() {
+            if i > 0 && receipt.block_number != receipts[i - 1].block_number {
+                store.seal(receipts[i - 1].block_number);
+                if i == 2 {
+                    mark = store.mark();
+                }
+            }
+            store.push(receipt.logs[0].log.address, i, 0);
+        }
+
+        let blocks = |store: &LogStore, filter: LogFilter| {
+            store
+                .query(&filter, |i| &receipts[i])
+                .iter()
+                .map(|log| log.block_number)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![1, 1, 2, 3], blocks(&store, LogFilter::default()));
+        assert_eq!(
+            vec![1, 2],
+            blocks(
+                &store,
+                LogFilter {
+                    to_block: Some(2),
+                    addresses: vec![Address::repeat_byte(1), Address::repeat_byte(1)],
+                    ..Default::default()
+                }
+            )
+        );
+        // wildcard and OR
+        assert_eq!(
+            vec![1, 1, 2],
+            blocks(
+                &store,
+                LogFilter {
+                    topics: vec![None, Some(vec![B256::repeat_byte(2), B256::repeat_byte(3)])],
+                    ..Default::default()
+                }
+            )
+        );
+        assert_eq!(
+            vec![2],
+            blocks(
+                &store,
+                LogFilter {
+                    from_block: Some(2),
+                    addresses: vec![Address::repeat_byte(1)],
+                    topics: vec![Some(vec![B256::repeat_byte(1)])],
+                    ..Default::default()
+                }
+            )
+        );
+
+        assert!(blocks(
+            &store,
+            LogFilter {
+                from_block: Some(3),
+                to_block: Some(2),
+                ..Default::default()
+            }
+        )
+        .is_empty());
+
+        store.rollback(mark);
+        assert_eq!(vec![1, 1], blocks(&store, LogFilter::default()));
+        // a block number set back
+        store.push(Address::repeat_byte(1), 0, 0);
+        assert_eq!(
+            vec![1, 1],
+            blocks(
+                &store,
+                LogFilter {
+                    addresses: vec![Address::repeat_byte(1)],
+                    ..Default::default()
+                }
+            )
+        );
+        let to_block = |to: u64| LogFilter {
+            to_block: Some(to),
+            ..Default::default()
+        };
+        assert_eq!(vec![1, 1, 1], blocks(&store, to_block(1)));
+
+        // mined blocks are found by the number they were mined with, and go back to
+        // the current block on rollback
+        let mark = store.mark();
+        store.seal(1);
+        store.push(Address::repeat_byte(2), 3, 0);
+        assert_eq!(vec![1, 1, 1], blocks(&store, to_block(2)));
+        assert_eq!(vec![1, 1, 1, 3], blocks(&store, LogFilter::default()));
+        store.rollback(mark);
+        assert_eq!(vec![1, 1, 1], blocks(&store, LogFilter::default()));
+        store.seal(1);
+        assert_eq!(vec![1, 1, 1], blocks(&store, to_block(1)));
+    }
+}
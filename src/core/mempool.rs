@@ -0,0 +1,156 @@
+//!
+//! Queue transactions to be mined into a block.  See `BaseEvm::send_transaction`
+//! and `BaseEvm::mine_block`
+//!
+use alloy_primitives::{keccak256, Address, B256, U256};
+use revm::primitives::{TransactTo, TxEnv};
+
+use crate::core::evm::TxRequest;
+
+/// When blocks are mined
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MiningMode {
+    /// Blocks are only mined by `BaseEvm::mine_block` or `BaseEvm::update_block`
+    #[default]
+    Manual,
+    /// A block is mined after each transaction. The timestamp of each block is
+    /// the given number of seconds after the previous one
+    Auto(u64),
+    /// A block is mined after every `transactions` transactions. The timestamp of
+    /// each block is `block_time` seconds after the previous one
+    Batch { transactions: u64, block_time: u64 },
+    /// A block is mined every `interval` seconds of block time.  Time only moves with
+    /// `BaseEvm::warp`: warping `interval` seconds or more past the start of the current
+    /// block mines it, and the next block starts at the new timestamp
+    Interval(u64),
+}
+
+/// The order pending transactions are mined in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxOrdering {
+    /// First in, first out
+    #[default]
+    Fifo,
+    /// Highest priority fee per gas first.  Transactions with the same fee are FIFO
+    PriorityFee,
+}
+
+/// A transaction waiting to be mined
+#[derive(Clone, Debug)]
+pub struct PendingTx {
+    pub hash: B256,
+    pub request: TxRequest,
+}
+
+/// A pending transaction that `BaseEvm::mine_block` dropped because it's invalid,
+/// e.g. its gas price is below the base fee
+#[derive(Debug)]
+pub struct DroppedTx {
+    pub hash: B256,
+    pub error: anyhow::Error,
+}
+
+/// Transactions waiting to be mined
+#[derive(Clone, Debug, Default)]
+pub struct Mempool {
+    pub ordering: TxOrdering,
+    pending: Vec<PendingTx>,
+    // number of transactions sent, used to make each hash unique
+    sequence: u64,
+}
+
+impl Mempool {
+    /// Queue a transaction, returning its hash
+    pub fn push(&mut self, request: TxRequest) -> B256 {
+        let hash = self.hash(
+            request.caller,
+            request.to.unwrap_or_default(),
+            request.value,
+            &request.data,
+        );
+        self.pending.push(PendingTx { hash, request });
+        hash
+    }
+
+    /// A hash for a transaction that was committed without going through the mempool
+    pub fn next_hash(&mut self, tx: &TxEnv) -> B256 {
+        let to = match tx.transact_to {
+            TransactTo::Call(to) => to,
+            TransactTo::Create(_) => Address::ZERO,
+        };
+        self.hash(tx.caller, to, tx.value, &tx.data)
+    }
+
+    // A deterministic hash for the next transaction.  This is not the RLP hash
+    // used by Ethereum, as transactions are not signed.
+    fn hash(&mut self, caller: Address, to: Address, value: U256, data: &[u8]) -> B256 {
+        self.sequence += 1;
+        keccak256(
+            [
+                caller.as_slice(),
+                to.as_slice(),
+                &value.to_be_bytes::<32>(),
+                data,
+                &self.sequence.to_be_bytes(),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Hashes of the pending transactions in the order they were sent
+    pub fn hashes(&self) -> Vec<B256> {
+        self.pending.iter().map(|tx| tx.hash).collect()
+    }
+
+    /// Remove the pending transactions in the order they should be mined. `tip`
+    /// is the priority fee paid per unit of gas by a transaction
+    pub fn take(&mut self, tip: impl Fn(&TxRequest) -> U256) -> Vec<PendingTx> {
+        let mut pending = std::mem::take(&mut self.pending);
+        if self.ordering == TxOrdering::PriorityFee {
+            // stable, so ties stay in the order they were sent
+            pending.sort_by_cached_key(|tx| std::cmp::Reverse(tip(&tx.request)));
+        }
+        pending
+    }
+
+    /// Return transactions that were not mined to the front of the queue
+    pub fn requeue(&mut self, txs: Vec<PendingTx>) {
+        self.pending.splice(0..0, txs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordering() {
+        let tx = |value: u64| TxRequest {
+            caller: Address::repeat_byte(1),
+            value: U256::from(value),
+            ..Default::default()
+        };
+        let tip = |request: &TxRequest| request.value;
+
+        let mut pool = Mempool::default();
+        let hashes = [1, 3, 2, 3].map(|value| pool.push(tx(value)));
+        // the same transaction sent twice has a different hash
+        assert_ne!(hashes[1], hashes[3]);
+        assert_eq!(hashes.to_vec(), pool.hashes());
+
+        let fifo = pool.take(tip);
+        assert!(pool.hashes().is_empty());
+        assert_eq!(
+            hashes.to_vec(),
+            fifo.iter().map(|tx| tx.hash).collect::<Vec<_>>()
+        );
+
+        pool.requeue(fifo);
+        pool.ordering = TxOrdering::PriorityFee;
+        let by_fee = pool.take(tip);
+        assert_eq!(
+            vec![hashes[1], hashes[3], hashes[2], hashes[0]],
+            by_fee.iter().map(|tx| tx.hash).collect::<Vec<_>>()
+        );
+    }
+}
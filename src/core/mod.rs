@@ -1,9 +1,22 @@
 pub mod abi;
+pub mod access_list;
+pub mod block;
+pub mod call_tracer;
+pub mod console;
+pub mod coverage;
 pub mod evm;
 
 pub mod errors;
 pub mod fork;
 pub mod fork_backend;
+pub mod gas_profiler;
+pub mod hardfork;
+pub mod history;
 pub mod in_memory_db;
+pub mod inspector;
+pub mod logs;
+pub mod mempool;
+pub mod precompile;
 pub mod snapshot;
 pub mod storage;
+pub mod struct_logger;
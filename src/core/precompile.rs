@@ -0,0 +1,150 @@
+//!
+//! Custom precompiles implemented in Rust.  They're registered with the EVM at chosen
+//! addresses, see `BaseEvm::register_precompile`.
+//!
+use alloy_primitives::{Address, Bytes};
+use revm::{
+    handler::register::HandleRegisterBox,
+    interpreter::{Gas, InstructionResult},
+    precompile::{Precompile, PrecompileResult},
+    primitives::{Env, PrecompileError, StatefulPrecompile},
+    ContextPrecompile, ContextPrecompiles, Database, FrameOrResult, FrameResult,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Why a custom precompile failed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CustomPrecompileError {
+    /// Revert the call with `output`.  Only `gas_used` is used, the rest of the gas
+    /// is returned to the caller
+    Revert { output: Bytes, gas_used: u64 },
+    /// Fail the call and use all of its gas, like the standard precompiles
+    Halt(PrecompileError),
+}
+
+impl From<PrecompileError> for CustomPrecompileError {
+    fn from(err: PrecompileError) -> Self {
+        CustomPrecompileError::Halt(err)
+    }
+}
+
+/// The gas used and the output of a custom precompile, or why it failed
+pub type CustomPrecompileResult = Result<(u64, Bytes), CustomPrecompileError>;
+
+/// A precompile implemented in Rust
+pub trait CustomPrecompile: Send + Sync {
+    /// Run the precompile with the `input` of the call and the `gas_limit` available.
+    /// Return the gas used and the output, or an error.
+    fn call(&self, input: &Bytes, gas_limit: u64) -> CustomPrecompileResult;
+}
+
+impl<F> CustomPrecompile for F
+where
+    F: Fn(&Bytes, u64) -> CustomPrecompileResult + Send + Sync,
+{
+    fn call(&self, input: &Bytes, gas_limit: u64) -> CustomPrecompileResult {
+        self(input, gas_limit)
+    }
+}
+
+/// A precompile that always returns the same output, e.g. to stub an oracle
+#[derive(Clone, Debug)]
+pub struct StubPrecompile {
+    pub output: Bytes,
+    pub gas_used: u64,
+}
+
+impl CustomPrecompile for StubPrecompile {
+    fn call(&self, _input: &Bytes, gas_limit: u64) -> CustomPrecompileResult {
+        if self.gas_used > gas_limit {
+            return Err(PrecompileError::OutOfGas.into());
+        }
+        Ok((self.gas_used, self.output.clone()))
+    }
+}
+
+/// Custom precompiles by address
+pub type CustomPrecompiles = HashMap<Address, Arc<dyn CustomPrecompile>>;
+
+// The output and gas used of a precompile that reverted
+type Reverted = Arc<Mutex<Option<(Bytes, u64)>>>;
+
+// Adapts a custom precompile to revm.  revm precompiles can't revert, so a revert
+// fails the precompile and is saved in `reverted` for the call handler
+struct Adapter {
+    precompile: Arc<dyn CustomPrecompile>,
+    reverted: Reverted,
+}
+
+impl StatefulPrecompile for Adapter {
+    fn call(&self, input: &Bytes, gas_limit: u64, _env: &Env) -> PrecompileResult {
+        match self.precompile.call(input, gas_limit) {
+            Ok(result) => Ok(result),
+            Err(CustomPrecompileError::Halt(err)) => Err(err),
+            Err(CustomPrecompileError::Revert { output, gas_used }) => {
+                *self.reverted.lock().unwrap() = Some((output, gas_used));
+                Err(PrecompileError::other("reverted"))
+            }
+        }
+    }
+}
+
+/// Add the `precompiles` to those of the hardfork used by the EVM.  A custom
+/// precompile replaces a standard precompile at the same address.
+pub fn precompiles_handle_register<EXT, DB: Database>(
+    precompiles: CustomPrecompiles,
+) -> HandleRegisterBox<EXT, DB> {
+    Box::new(move |handler| {
+        if precompiles.is_empty() {
+            return;
+        }
+        let reverted = Reverted::default();
+
+        // precompiles run in the call handler, so the revert, if any, is from this call
+        let call = handler.execution.call.clone();
+        let call_reverted = reverted.clone();
+        handler.execution.call = Arc::new(move |context, inputs| {
+            let mut result = call(context, inputs)?;
+            let Some((output, gas_used)) = call_reverted.lock().unwrap().take() else {
+                return Ok(result);
+            };
+            if let FrameOrResult::Result(FrameResult::Call(outcome)) = &mut result {
+                let mut gas = Gas::new(outcome.result.gas.limit());
+                outcome.result.result = if gas.record_cost(gas_used) {
+                    outcome.result.output = output;
+                    InstructionResult::Revert
+                } else {
+                    InstructionResult::PrecompileOOG
+                };
+                outcome.result.gas = gas;
+            }
+            Ok(result)
+        });
+
+        let standard = handler.pre_execution.load_precompiles();
+        let custom = precompiles.clone();
+        handler.pre_execution.load_precompiles = Arc::new(move || {
+            // `ContextPrecompiles` is only `Clone` if the database is
+            let mut all = ContextPrecompiles::default();
+            all.extend(
+                standard
+                    .iter()
+                    .map(|(address, precompile)| (*address, precompile.clone())),
+            );
+            all.extend(custom.iter().map(|(address, precompile)| {
+                let adapter = Adapter {
+                    precompile: precompile.clone(),
+                    reverted: reverted.clone(),
+                };
+                (
+                    *address,
+                    ContextPrecompile::Ordinary(Precompile::Stateful(Arc::new(adapter))),
+                )
+            }));
+            all
+        });
+    })
+}
@@ -1,6 +1,7 @@
 //!
 //! Containers for serializing EVM state information
 //!
+use crate::core::block::BlockConfig;
 use revm::primitives::{Address, Bytes, U256};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
@@ -29,5 +30,11 @@ pub struct SnapShot {
     pub source: SnapShotSource,
     pub block_num: u64,
     pub timestamp: u64,
+    /// name of the hardfork used by the EVM.  See `hardfork::parse_spec_id`
+    #[serde(default)]
+    pub spec: Option<String>,
+    /// block and chain settings used by the EVM
+    #[serde(default)]
+    pub block: Option<BlockConfig>,
     pub accounts: BTreeMap<Address, SnapShotAccountRecord>,
 }
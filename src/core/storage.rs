@@ -5,15 +5,27 @@
 use alloy_primitives::{Address, U256};
 use anyhow::{anyhow, Result};
 use revm::{
+    db::{AccountState, DbAccount},
+    inspector_handle_register,
     interpreter::primitives::EnvWithHandlerCfg,
     primitives::{
-        Account, AccountInfo, Bytecode, HashMap as Map, ResultAndState, B256, KECCAK_EMPTY,
+        Account, AccountInfo, Bytecode, EVMError, HashMap as Map, InvalidTransaction,
+        ResultAndState, B256, KECCAK_EMPTY,
     },
-    Database, DatabaseCommit, DatabaseRef, EvmBuilder,
+    Database, DatabaseCommit, DatabaseRef, EvmBuilder, GetInspector,
+};
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
 };
-use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::core::{errors::DatabaseError, snapshot::SnapShot};
+use crate::core::{
+    block::BlockConfig,
+    errors::{DatabaseError, ExecutionError},
+    inspector::InspectorStack,
+    precompile::{precompiles_handle_register, CustomPrecompiles},
+    snapshot::SnapShot,
+};
 use crate::core::{fork::Fork, in_memory_db::MemDb};
 
 /// Information related to creating a fork
@@ -42,6 +54,29 @@ impl CreateFork {
 }
 */
 
+/// The backend state saved by `StorageBackend::checkpoint`.  Changes to the database
+/// are undone with the journal, so a checkpoint doesn't copy the state.
+#[derive(Clone, Debug)]
+struct Checkpoint {
+    // length of the journal when the checkpoint was taken
+    journal_len: usize,
+    block_number: u64,
+    timestamp: u64,
+    basefee: U256,
+    block_gas_used: u64,
+}
+
+/// How to undo a change to the accounts cached by the database
+#[derive(Clone, Debug)]
+enum Undo {
+    /// the info and state of an account before it changed. None if it wasn't cached
+    Account(Address, Option<(AccountInfo, AccountState)>),
+    /// the value of a storage slot before it changed. None if it wasn't cached
+    Slot(Address, U256, Option<U256>),
+    /// the storage of an account before it was cleared
+    Storage(Address, Map<U256, U256>),
+}
+
 // Used by the EVM to access storage.  This can either be an in-memory only db or a forked db.
 // The EVM delegates transact() and transact_commit to this module
 //
@@ -51,6 +86,13 @@ pub struct StorageBackend {
     forkdb: Option<Fork>,
     pub block_number: u64, // used to record in the snapshot...
     pub timestamp: u64,
+    pub basefee: U256, // may change block to block. see BaseEvm::set_fee_market
+    pub block_gas_used: u64, // gas used by transactions in the current block
+    checkpoints: BTreeMap<u64, Checkpoint>,
+    next_checkpoint_id: u64,
+    // changes made since the first checkpoint, in order
+    journal: Vec<Undo>,
+    pub precompiles: CustomPrecompiles, // added to the standard precompiles. see BaseEvm::register_precompile
 }
 
 impl Default for StorageBackend {
@@ -70,6 +112,12 @@ impl StorageBackend {
                 forkdb: Some(backend),
                 block_number,
                 timestamp,
+                basefee: U256::ZERO,
+                block_gas_used: 0,
+                checkpoints: BTreeMap::new(),
+                next_checkpoint_id: 0,
+                journal: Vec::new(),
+                precompiles: CustomPrecompiles::new(),
             }
         } else {
             let timestamp = SystemTime::now()
@@ -81,11 +129,28 @@ impl StorageBackend {
                 forkdb: None,
                 block_number: 1,
                 timestamp,
+                basefee: U256::ZERO,
+                block_gas_used: 0,
+                checkpoints: BTreeMap::new(),
+                next_checkpoint_id: 0,
+                journal: Vec::new(),
+                precompiles: CustomPrecompiles::new(),
             }
         }
     }
 
+    /// Is the backend using a forked database?
+    pub fn is_fork(&self) -> bool {
+        self.forkdb.is_some()
+    }
+
+    /// Block information loaded from the remote node when using a fork
+    pub fn fork_block_config(&self) -> Option<BlockConfig> {
+        self.forkdb.as_ref().map(|fork| fork.block_config.clone())
+    }
+
     pub fn insert_account_info(&mut self, address: Address, info: AccountInfo) {
+        self.record_account(address);
         if let Some(fork) = self.forkdb.as_mut() {
             fork.database_mut().insert_account_info(address, info)
         } else {
@@ -94,22 +159,25 @@ impl StorageBackend {
         }
     }
 
-    /*
+    /// Set the value of a storage `slot` for the account. The account is loaded
+    /// from the database (or remote node when forking) if needed.
     pub fn insert_account_storage(
         &mut self,
         address: Address,
         slot: U256,
         value: U256,
     ) -> Result<(), DatabaseError> {
-        let ret = if let Some(fork) = self.forkdb.as_mut() {
+        self.record_account(address);
+        self.record_slot(address, slot);
+        if let Some(fork) = self.forkdb.as_mut() {
             fork.database_mut()
                 .insert_account_storage(address, slot, value)
         } else {
             self.mem_db.db.insert_account_storage(address, slot, value)
-        };
-        ret
+        }
     }
 
+    /*
     pub fn replace_account_storage(
         &mut self,
         address: Address,
@@ -124,11 +192,27 @@ impl StorageBackend {
     }
     */
 
-    pub fn run_transact(&mut self, env: &mut EnvWithHandlerCfg) -> Result<ResultAndState> {
-        let mut evm = create_evm(self, env.clone());
+    /// Execute the transaction. The `inspector` is only used if at least
+    /// one of its inspectors is enabled.
+    pub fn run_transact(
+        &mut self,
+        env: &mut EnvWithHandlerCfg,
+        inspector: &mut InspectorStack,
+    ) -> Result<ResultAndState> {
+        let precompiles = self.precompiles.clone();
+        if inspector.is_empty() {
+            let mut evm = create_evm(self, env.clone(), precompiles);
+            let res = evm
+                .transact()
+                .map_err(|e| transact_error(e, env.tx.gas_limit))?;
+            env.env = evm.context.evm.inner.env;
+            return Ok(res);
+        }
+
+        let mut evm = create_evm_with_inspector(self, env.clone(), inspector, precompiles);
         let res = evm
             .transact()
-            .map_err(|e| anyhow!("backend failed while executing transaction:  {:?}", e))?;
+            .map_err(|e| transact_error(e, env.tx.gas_limit))?;
         env.env = evm.context.evm.inner.env;
 
         Ok(res)
@@ -151,6 +235,8 @@ impl StorageBackend {
         self.timestamp = snapshot.timestamp;
 
         for (addr, account) in snapshot.accounts.into_iter() {
+            self.record_account(addr);
+            self.record_storage(addr);
             // note: this will populate both 'accounts' and 'contracts'
             self.mem_db.db.insert_account_info(
                 addr,
@@ -181,10 +267,140 @@ impl StorageBackend {
         }
     }
 
+    /// Save the current state and return the id of the checkpoint.  Checkpoints may be
+    /// nested.  The state isn't copied: changes made after the first checkpoint are
+    /// recorded in a journal, and undone by `revert_to`.
+    pub fn checkpoint(&mut self) -> u64 {
+        let id = self.next_checkpoint_id;
+        self.next_checkpoint_id += 1;
+        self.checkpoints.insert(
+            id,
+            Checkpoint {
+                journal_len: self.journal.len(),
+                block_number: self.block_number,
+                timestamp: self.timestamp,
+                basefee: self.basefee,
+                block_gas_used: self.block_gas_used,
+            },
+        );
+        id
+    }
+
+    /// Restore the state saved by checkpoint `id`. The checkpoint, and any
+    /// checkpoints taken after it, are removed.  Returns false if `id` is unknown.
+    pub fn revert_to(&mut self, id: u64) -> bool {
+        let Some(checkpoint) = self.checkpoints.remove(&id) else {
+            return false;
+        };
+        let _ = self.checkpoints.split_off(&id);
+
+        let undo = self.journal.split_off(checkpoint.journal_len);
+        let accounts = self.accounts_mut();
+        for change in undo.into_iter().rev() {
+            match change {
+                Undo::Account(address, None) => {
+                    accounts.remove(&address);
+                }
+                Undo::Account(address, Some((info, state))) => {
+                    let account = accounts.entry(address).or_default();
+                    account.info = info;
+                    account.account_state = state;
+                }
+                Undo::Slot(address, slot, None) => {
+                    if let Some(account) = accounts.get_mut(&address) {
+                        account.storage.remove(&slot);
+                    }
+                }
+                Undo::Slot(address, slot, Some(value)) => {
+                    accounts
+                        .entry(address)
+                        .or_default()
+                        .storage
+                        .insert(slot, value);
+                }
+                Undo::Storage(address, storage) => {
+                    accounts.entry(address).or_default().storage = storage;
+                }
+            }
+        }
+        if self.checkpoints.is_empty() {
+            self.journal.clear();
+        }
+
+        self.block_number = checkpoint.block_number;
+        self.timestamp = checkpoint.timestamp;
+        self.basefee = checkpoint.basefee;
+        self.block_gas_used = checkpoint.block_gas_used;
+        true
+    }
+
+    /// Remove checkpoint `id` without changing the current state.
+    /// Returns false if `id` is unknown.
+    pub fn discard(&mut self, id: u64) -> bool {
+        let found = self.checkpoints.remove(&id).is_some();
+        if self.checkpoints.is_empty() {
+            self.journal.clear();
+        }
+        found
+    }
+
+    // The accounts cached by the database in use
+    fn accounts(&self) -> &Map<Address, DbAccount> {
+        match self.forkdb.as_ref() {
+            Some(fork) => &fork.database().accounts,
+            None => &self.mem_db.db.accounts,
+        }
+    }
+
+    fn accounts_mut(&mut self) -> &mut Map<Address, DbAccount> {
+        match self.forkdb.as_mut() {
+            Some(fork) => &mut fork.database_mut().accounts,
+            None => &mut self.mem_db.db.accounts,
+        }
+    }
+
+    // Record the info of an account before it changes, if there's a checkpoint
+    fn record_account(&mut self, address: Address) {
+        if self.checkpoints.is_empty() {
+            return;
+        }
+        let before = self
+            .accounts()
+            .get(&address)
+            .map(|account| (account.info.clone(), account.account_state.clone()));
+        self.journal.push(Undo::Account(address, before));
+    }
+
+    // Record a storage slot before it changes, if there's a checkpoint
+    fn record_slot(&mut self, address: Address, slot: U256) {
+        if self.checkpoints.is_empty() {
+            return;
+        }
+        let before = self
+            .accounts()
+            .get(&address)
+            .and_then(|account| account.storage.get(&slot).copied());
+        self.journal.push(Undo::Slot(address, slot, before));
+    }
+
+    // Record all the storage of an account before it's cleared, if there's a checkpoint
+    fn record_storage(&mut self, address: Address) {
+        if self.checkpoints.is_empty() {
+            return;
+        }
+        let before = self
+            .accounts()
+            .get(&address)
+            .map(|account| account.storage.clone())
+            .unwrap_or_default();
+        self.journal.push(Undo::Storage(address, before));
+    }
+
     /// See EVM update_block
     pub fn update_block_info(&mut self, interval: u64) {
         self.block_number += 1;
         self.timestamp += interval;
+        self.block_gas_used = 0;
     }
 }
 
@@ -261,6 +477,17 @@ impl Database for StorageBackend {
 
 impl DatabaseCommit for StorageBackend {
     fn commit(&mut self, changes: Map<Address, Account>) {
+        for (address, account) in changes.iter().filter(|(_, account)| account.is_touched()) {
+            self.record_account(*address);
+            if account.is_selfdestructed() || account.is_created() {
+                self.record_storage(*address);
+            } else {
+                account
+                    .storage
+                    .keys()
+                    .for_each(|slot| self.record_slot(*address, *slot));
+            }
+        }
         if let Some(db) = self.forkdb.as_mut() {
             db.commit(changes)
         } else {
@@ -272,9 +499,41 @@ impl DatabaseCommit for StorageBackend {
 fn create_evm<'a, DB: Database>(
     db: DB,
     env: revm::primitives::EnvWithHandlerCfg,
+    precompiles: CustomPrecompiles,
 ) -> revm::Evm<'a, (), DB> {
     EvmBuilder::default()
         .with_db(db)
-        .with_env(env.env.clone())
+        .with_env_with_handler_cfg(env)
+        .append_handler_register_box(precompiles_handle_register(precompiles))
         .build()
 }
+
+fn create_evm_with_inspector<'a, DB: Database, I: GetInspector<DB>>(
+    db: DB,
+    env: revm::primitives::EnvWithHandlerCfg,
+    inspector: I,
+    precompiles: CustomPrecompiles,
+) -> revm::Evm<'a, I, DB> {
+    EvmBuilder::default()
+        .with_db(db)
+        .with_external_context(inspector)
+        .with_env_with_handler_cfg(env)
+        .append_handler_register_box(precompiles_handle_register(precompiles))
+        .append_handler_register(inspector_handle_register)
+        .build()
+}
+
+// Keep database and out of gas errors typed, see `ExecutionError`
+fn transact_error(err: EVMError<DatabaseError>, gas_limit: u64) -> anyhow::Error {
+    match err {
+        EVMError::Database(err) => ExecutionError::Database(err).into(),
+        EVMError::Transaction(InvalidTransaction::CallGasCostMoreThanGasLimit) => {
+            ExecutionError::OutOfGas {
+                gas_limit,
+                gas_used: 0,
+            }
+            .into()
+        }
+        err => anyhow!("backend failed while executing transaction:  {:?}", err),
+    }
+}
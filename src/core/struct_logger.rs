@@ -0,0 +1,174 @@
+//!
+//! Opcode level tracer.  The output has the same JSON shape as the default
+//! struct logger used by geth's `debug_traceTransaction`
+//!
+use alloy_primitives::{hex, Address, U256};
+use revm::{
+    interpreter::{opcode, InstructionResult, Interpreter, OpCode},
+    Database, EvmContext, Inspector,
+};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Options for the struct logger. Same as geth's `LogConfig`
+#[derive(Clone, Debug, Default)]
+pub struct StructLoggerConfig {
+    /// include memory in each step
+    pub enable_memory: bool,
+    /// exclude the stack from each step
+    pub disable_stack: bool,
+    /// exclude storage from SLOAD/SSTORE steps
+    pub disable_storage: bool,
+}
+
+/// A single step of execution
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: u64,
+    pub op: String,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub refund: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The result of tracing a transaction.  Serialize to JSON to compare with
+/// the output of `debug_traceTransaction`
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLogTrace {
+    /// gas used by the transaction
+    pub gas: u64,
+    /// did the transaction revert or halt?
+    pub failed: bool,
+    /// hex encoded output of the transaction (no `0x` prefix)
+    pub return_value: String,
+    pub struct_logs: Vec<StructLog>,
+}
+
+/// An `Inspector` that records each step of execution
+#[derive(Debug, Default)]
+pub struct StructLogger {
+    config: StructLoggerConfig,
+    logs: Vec<StructLog>,
+    // storage slots seen by SLOAD/SSTORE for each contract
+    storage: HashMap<Address, BTreeMap<U256, U256>>,
+    // gas remaining at the start of the current step
+    gas_remaining: u64,
+    // contract and slot of the current SLOAD. The value is known after the step
+    pending_sload: Option<(Address, U256)>,
+}
+
+impl StructLogger {
+    pub fn new(config: StructLoggerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Return the steps recorded
+    pub fn into_logs(self) -> Vec<StructLog> {
+        self.logs
+    }
+
+    // Record a storage slot for the contract and return a copy of the
+    // contract's storage in the geth format
+    fn record_storage(
+        &mut self,
+        address: Address,
+        slot: U256,
+        value: U256,
+    ) -> BTreeMap<String, String> {
+        let storage = self.storage.entry(address).or_default();
+        storage.insert(slot, value);
+        storage.iter().map(|(k, v)| (word(*k), word(*v))).collect()
+    }
+}
+
+impl<DB: Database> Inspector<DB> for StructLogger {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        let op = interp.current_opcode();
+        let stack = interp.stack.data();
+        self.gas_remaining = interp.gas.remaining();
+
+        let storage = match op {
+            opcode::SSTORE if !self.config.disable_storage && stack.len() >= 2 => {
+                let slot = stack[stack.len() - 1];
+                let value = stack[stack.len() - 2];
+                Some(self.record_storage(interp.contract.address, slot, value))
+            }
+            // the value is recorded in `step_end`, once it's been loaded
+            opcode::SLOAD if !self.config.disable_storage && !stack.is_empty() => {
+                self.pending_sload = Some((interp.contract.address, stack[stack.len() - 1]));
+                None
+            }
+            _ => None,
+        };
+
+        self.logs.push(StructLog {
+            pc: interp.program_counter() as u64,
+            op: OpCode::new(op)
+                .map(|op| op.as_str().to_string())
+                .unwrap_or_else(|| format!("opcode {:#x} not defined", op)),
+            gas: self.gas_remaining,
+            gas_cost: 0,
+            depth: context.journaled_state.depth(),
+            stack: (!self.config.disable_stack)
+                .then(|| stack.iter().map(|v| format!("{:#x}", v)).collect()),
+            memory: self.config.enable_memory.then(|| {
+                interp
+                    .shared_memory
+                    .context_memory()
+                    .chunks(32)
+                    .map(hex::encode)
+                    .collect()
+            }),
+            storage,
+            refund: interp.gas.refunded().max(0) as u64,
+            error: None,
+        });
+    }
+
+    fn step_end(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        let sload = self.pending_sload.take();
+        let Some(log) = self.logs.last_mut() else {
+            return;
+        };
+        log.gas_cost = self.gas_remaining.saturating_sub(interp.gas.remaining());
+        if interp.instruction_result.is_error() {
+            log.error = Some(format!("{:?}", interp.instruction_result));
+        }
+
+        if let (Some((address, slot)), InstructionResult::Continue) =
+            (sload, interp.instruction_result)
+        {
+            // the top of the stack is the loaded value
+            if let Ok(value) = interp.stack.peek(0) {
+                let storage = self.record_storage(address, slot, value);
+                if let Some(log) = self.logs.last_mut() {
+                    log.storage = Some(storage);
+                }
+            }
+        }
+    }
+}
+
+// Format as a 32 byte hex word with no `0x` prefix
+fn word(value: U256) -> String {
+    hex::encode(value.to_be_bytes::<32>())
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
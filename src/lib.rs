@@ -19,6 +19,8 @@ fn simular(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<pyabi::PyAbi>()?;
     m.add_class::<pyevm::PyEvm>()?;
     m.add_class::<pyevm::TxResult>()?;
+    m.add("RevertError", m.py().get_type::<pyevm::RevertError>())?;
+    m.add("OutOfGasError", m.py().get_type::<pyevm::OutOfGasError>())?;
     Ok(())
 }
 
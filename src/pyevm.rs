@@ -1,9 +1,36 @@
-use crate::core::{evm::BaseEvm, evm::CallResult, snapshot::SnapShot, storage::CreateFork};
+use crate::core::{
+    abi::decode_builtin_error,
+    access_list::AccessList,
+    block::{BlockHeader, FeeMarket},
+    call_tracer::CallTrace,
+    coverage::ContractSources,
+    errors::{BundleError, ExecutionError},
+    evm::BaseEvm,
+    evm::{create2_address, create_address, CallResult, TxOptions, TxRequest, CREATE2_FACTORY},
+    gas_profiler::GasReport,
+    hardfork::{parse_spec_id, spec_id_name},
+    history::{Receipt, Transaction},
+    logs::LogFilter,
+    mempool::{MiningMode, TxOrdering},
+    precompile::{CustomPrecompile, CustomPrecompileError, CustomPrecompileResult, StubPrecompile},
+    snapshot::SnapShot,
+    storage::CreateFork,
+    struct_logger::{StructLogTrace, StructLoggerConfig},
+};
 use alloy_dyn_abi::DynSolValue;
-use alloy_primitives::U256;
-use anyhow::{anyhow, Result};
+use alloy_primitives::{Address, Bytes, Log, B256, U256};
+use alloy_sol_types::{Revert, SolError};
+use anyhow::{anyhow, bail, Result};
 use core::ffi::c_uchar;
-use pyo3::{ffi, prelude::*, IntoPyObjectExt};
+use pyo3::{
+    create_exception,
+    exceptions::PyException,
+    ffi,
+    prelude::*,
+    types::{PyBytes, PyDict, PyTuple},
+    IntoPyObjectExt,
+};
+use revm::primitives::PrecompileError;
 use std::collections::HashMap;
 
 use crate::{
@@ -11,6 +38,88 @@ use crate::{
     str_to_address,
 };
 
+/// An entry of an EIP-2930 access list passed from Python
+#[derive(FromPyObject)]
+pub struct AccessListItem {
+    #[pyo3(item)]
+    address: String,
+    #[pyo3(item)]
+    storage_keys: Vec<String>,
+}
+
+/// A Python int passed as a `U256`, e.g. a gas price.  Fails if it's negative or
+/// doesn't fit in 256 bits
+pub struct PyU256(U256);
+
+impl<'py> FromPyObject<'py> for PyU256 {
+    fn extract_bound(value: &Bound<'py, PyAny>) -> PyResult<Self> {
+        Ok(Self(int_to_u256(value)?))
+    }
+}
+
+/// A single value or a list of values passed from Python, e.g. the addresses
+/// and topics of `get_logs`
+#[derive(FromPyObject)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+/// A transaction in a bundle passed from Python. See `Function.build_transaction`
+#[derive(FromPyObject)]
+pub struct BundleTx<'py> {
+    #[pyo3(item)]
+    fn_name: String,
+    #[pyo3(item)]
+    args: String,
+    #[pyo3(item)]
+    caller: String,
+    #[pyo3(item)]
+    to: String,
+    #[pyo3(item)]
+    abi: PyRef<'py, PyAbi>,
+    #[pyo3(item, default)]
+    value: u128,
+    #[pyo3(item, default)]
+    gas_limit: Option<u64>,
+}
+
+/// A contract and its sources passed from Python. See `PyEvm.coverage_lcov`
+#[derive(FromPyObject)]
+pub struct CoverageContract {
+    #[pyo3(item, default)]
+    address: Option<String>,
+    #[pyo3(item, default)]
+    bytecode: Option<Vec<u8>>,
+    #[pyo3(item)]
+    source_map: String,
+    #[pyo3(item)]
+    sources: HashMap<u32, (String, String)>,
+}
+
+create_exception!(
+    simular,
+    RevertError,
+    PyException,
+    "Raised when a transaction reverts. `name` and `error_args` are the decoded error, if known, and `data` is the raw revert data"
+);
+
+create_exception!(
+    simular,
+    OutOfGasError,
+    PyException,
+    "Raised when a transaction runs out of gas. `gas_limit` is the gas limit of the transaction"
+);
+
 /// default block interval for advancing block time (12s)
 const DEFAULT_BLOCK_INTERVAL: u64 = 12;
 
@@ -26,6 +135,23 @@ pub struct TxResult {
     pub event: Option<HashMap<String, PyObject>>,
     #[pyo3(get)]
     pub gas_used: u64,
+    #[pyo3(get)]
+    pub gas_refunded: u64,
+    /// the price paid per unit of gas
+    #[pyo3(get)]
+    pub effective_gas_price: PyObject,
+    /// the total fee paid by the caller
+    #[pyo3(get)]
+    pub fee: PyObject,
+    /// the tree of calls made by the transaction, if call tracing is enabled
+    #[pyo3(get)]
+    pub trace: Option<PyObject>,
+    /// messages logged with Hardhat's `console.log`
+    #[pyo3(get)]
+    pub console_logs: Vec<String>,
+    /// the hash of the transaction, if it was committed. See `PyEvm.get_receipt`
+    #[pyo3(get)]
+    pub tx_hash: Option<String>,
 }
 
 #[pyclass]
@@ -33,28 +159,49 @@ pub struct PyEvm(BaseEvm);
 
 #[pymethods]
 impl PyEvm {
-    /// Create an in-memory EVM
+    /// Create an in-memory EVM. Optionally set the hardfork `spec` (default: latest)
     #[new]
-    pub fn new() -> Self {
-        Self(BaseEvm::default())
+    #[pyo3(signature = (spec=None))]
+    pub fn new(spec: Option<&str>) -> Result<Self> {
+        let spec_id = spec.map(parse_spec_id).transpose()?;
+        Ok(Self(BaseEvm::new_with_spec(None, spec_id)))
     }
 
-    /// Create a fork EVM
+    /// Create a fork EVM. If `spec` is not set, the hardfork active at the
+    /// forked block is used.
     #[staticmethod]
-    #[pyo3(signature = (url, blocknumber=None))]
-    pub fn from_fork(url: &str, blocknumber: Option<u64>) -> Self {
+    #[pyo3(signature = (url, blocknumber=None, spec=None))]
+    pub fn from_fork(url: &str, blocknumber: Option<u64>, spec: Option<&str>) -> Result<Self> {
+        let spec_id = spec.map(parse_spec_id).transpose()?;
         let forkinfo = CreateFork {
             url: url.into(),
             blocknumber,
         };
-        Self(BaseEvm::new(Some(forkinfo)))
+        Ok(Self(BaseEvm::new_with_spec(Some(forkinfo), spec_id)))
     }
 
-    /// Create an in-memory EVM from a `SnapShot`
+    /// Create an in-memory EVM from a `SnapShot`. `spec` will override the
+    /// hardfork saved in the snapshot.
     #[staticmethod]
-    pub fn from_snapshot(raw: &str) -> Self {
+    #[pyo3(signature = (raw, spec=None))]
+    pub fn from_snapshot(raw: &str, spec: Option<&str>) -> Result<Self> {
         let snap: SnapShot = serde_json::from_str(raw).expect("unable to parse raw snapshot");
-        Self(BaseEvm::new_from_snapshot(snap))
+        let mut evm = BaseEvm::new_from_snapshot(snap);
+        if let Some(name) = spec {
+            evm.set_spec_id(parse_spec_id(name)?);
+        }
+        Ok(Self(evm))
+    }
+
+    /// Return the name of the hardfork used by the EVM
+    pub fn get_spec(&self) -> String {
+        spec_id_name(self.0.spec_id()).into()
+    }
+
+    /// Change the hardfork used by the EVM
+    pub fn set_spec(&mut self, spec: &str) -> Result<()> {
+        self.0.set_spec_id(parse_spec_id(spec)?);
+        Ok(())
     }
 
     /// Create a `SnapShot` of the current EVM state
@@ -63,38 +210,531 @@ impl PyEvm {
         serde_json::to_string_pretty(&snapshot).map_err(|e| anyhow!("{:?}", e))
     }
 
+    /// Save the current state in memory. Returns the checkpoint id
+    pub fn checkpoint(&mut self) -> u64 {
+        self.0.checkpoint()
+    }
+
+    /// Restore state to checkpoint `id`. Returns false if the checkpoint doesn't exist
+    pub fn revert_to(&mut self, id: u64) -> bool {
+        self.0.revert_to(id)
+    }
+
+    /// Remove checkpoint `id` without changing state. Returns false if the checkpoint doesn't exist
+    pub fn discard(&mut self, id: u64) -> bool {
+        self.0.discard(id)
+    }
+
+    /// Enable or disable the call tracer.  When enabled, `TxResult.trace` contains
+    /// the tree of calls made by the transaction.
+    pub fn set_call_tracing(&mut self, enabled: bool) {
+        self.0.set_call_tracing(enabled)
+    }
+
+    /// Is the call tracer enabled?
+    pub fn get_call_tracing(&self) -> bool {
+        self.0.call_tracing()
+    }
+
+    /// Enable or disable capturing messages logged with Hardhat's `console.log` in
+    /// `TxResult.console_logs`.  Disabled by default.  If `print` is true, they're also
+    /// printed to stdout, including those of transactions that fail.
+    #[pyo3(signature = (enabled, print=false))]
+    pub fn set_console_logs(&mut self, enabled: bool, print: bool) {
+        self.0.set_console_logs(enabled, print)
+    }
+
+    /// Are `console.log` messages captured?
+    pub fn get_console_logs(&self) -> bool {
+        self.0.console_logs()
+    }
+
+    /// Enable or disable enforcing the block gas limit.  When enabled, the gas limit of
+    /// a transaction must fit in the gas left in the block.
+    pub fn set_enforce_block_gas_limit(&mut self, enabled: bool) {
+        self.0.set_enforce_block_gas_limit(enabled)
+    }
+
+    /// Is the block gas limit enforced?
+    pub fn get_enforce_block_gas_limit(&self) -> bool {
+        self.0.enforce_block_gas_limit()
+    }
+
+    /// Enable or disable the gas profiler.  When enabled, the gas used by each
+    /// transaction is added to the report returned by `get_gas_report`.
+    pub fn set_gas_profiling(&mut self, enabled: bool) {
+        self.0.set_gas_profiling(enabled)
+    }
+
+    /// Is the gas profiler enabled?
+    pub fn get_gas_profiling(&self) -> bool {
+        self.0.gas_profiling()
+    }
+
+    /// Clear the gas report
+    pub fn reset_gas_report(&mut self) {
+        self.0.reset_gas_report()
+    }
+
+    /// Return the gas report.  `abis` is an optional map of contract address to
+    /// `PyAbi` used to resolve function selectors to names.
+    #[pyo3(signature = (abis=None))]
+    pub fn get_gas_report(
+        &self,
+        abis: Option<HashMap<String, PyRef<PyAbi>>>,
+        py: Python<'_>,
+    ) -> Result<PyObject> {
+        let abis = abis
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(address, abi)| Ok((str_to_address(&address)?, abi)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        gas_report_to_dict(self.0.gas_report(), &abis, py)
+    }
+
+    /// Enable or disable coverage.  When enabled, the instructions and branches
+    /// executed by each transaction are recorded for `coverage_lcov`.
+    pub fn set_coverage(&mut self, enabled: bool) {
+        self.0.set_coverage(enabled)
+    }
+
+    /// Is coverage enabled?
+    pub fn get_coverage(&self) -> bool {
+        self.0.coverage()
+    }
+
+    /// Clear the coverage
+    pub fn reset_coverage(&mut self) {
+        self.0.reset_coverage()
+    }
+
+    /// Return an LCOV report of the coverage of the `contracts`.  Each is a dict with
+    /// the runtime `bytecode`, or the `address` it's deployed at, the `source_map` of
+    /// the runtime bytecode from solc, and the `sources` by id: `{id: (path, content)}`
+    pub fn coverage_lcov(&self, contracts: Vec<CoverageContract>) -> Result<String> {
+        let contracts = contracts
+            .into_iter()
+            .map(|contract| {
+                let bytecode = match (contract.bytecode, contract.address) {
+                    (Some(bytecode), _) => bytecode.into(),
+                    (None, Some(address)) => self.0.get_code(str_to_address(&address)?)?,
+                    (None, None) => bail!("coverage: a contract needs an address or bytecode"),
+                };
+                Ok(ContractSources {
+                    bytecode,
+                    source_map: contract.source_map,
+                    sources: contract.sources,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.0.coverage_lcov(&contracts)
+    }
+
+    /// Enable the EIP-1559 fee market.  `block.basefee` will change each block
+    /// based on the gas used by the previous block.
+    #[pyo3(signature = (elasticity_multiplier=2, base_fee_max_change_denominator=8))]
+    pub fn enable_fee_market(
+        &mut self,
+        elasticity_multiplier: u64,
+        base_fee_max_change_denominator: u64,
+    ) -> Result<()> {
+        if elasticity_multiplier == 0 || base_fee_max_change_denominator == 0 {
+            bail!("fee market values must be greater than 0")
+        }
+        self.0.set_fee_market(Some(FeeMarket {
+            elasticity_multiplier,
+            base_fee_max_change_denominator,
+        }));
+        Ok(())
+    }
+
+    /// Disable the EIP-1559 fee market. The base fee stays at its current value
+    pub fn disable_fee_market(&mut self) {
+        self.0.set_fee_market(None)
+    }
+
+    /// Return the fee market settings as a dict, or None if the fee market is disabled
+    pub fn get_fee_market(&self) -> Option<HashMap<String, u64>> {
+        self.0.fee_market().map(|market| {
+            HashMap::from([
+                (
+                    "elasticity_multiplier".to_string(),
+                    market.elasticity_multiplier,
+                ),
+                (
+                    "base_fee_max_change_denominator".to_string(),
+                    market.base_fee_max_change_denominator,
+                ),
+            ])
+        })
+    }
+
+    /// Return the current `block.basefee`
+    pub fn get_basefee(&self, py: Python<'_>) -> PyObject {
+        convert_ints(self.0.basefee().to_le_bytes::<32>(), false, py)
+    }
+
+    /// Return the gas used by transactions in the current block
+    pub fn get_block_gas_used(&self) -> u64 {
+        self.0.block_gas_used()
+    }
+
+    /// Return the block configuration as a dict with the keys:
+    /// `chain_id`, `coinbase`, `gas_limit`, `basefee`, `prevrandao`, and `difficulty`
+    pub fn get_block_config(&self, py: Python<'_>) -> Result<HashMap<String, PyObject>> {
+        let config = self.0.block_config();
+        let mut map = HashMap::<String, PyObject>::new();
+        map.insert("chain_id".into(), config.chain_id.into_py_any(py)?);
+        map.insert(
+            "coinbase".into(),
+            config.coinbase.to_string().into_py_any(py)?,
+        );
+        map.insert("gas_limit".into(), config.gas_limit.into_py_any(py)?);
+        map.insert(
+            "basefee".into(),
+            convert_ints(config.basefee.to_le_bytes::<32>(), false, py),
+        );
+        map.insert(
+            "prevrandao".into(),
+            config.prevrandao.to_string().into_py_any(py)?,
+        );
+        map.insert(
+            "difficulty".into(),
+            convert_ints(config.difficulty.to_le_bytes::<32>(), false, py),
+        );
+        Ok(map)
+    }
+
+    /// Update the block configuration.  Only the values provided are changed.
+    #[pyo3(signature = (chain_id=None, coinbase=None, gas_limit=None, basefee=None, prevrandao=None, difficulty=None))]
+    pub fn set_block_config(
+        &mut self,
+        chain_id: Option<u64>,
+        coinbase: Option<&str>,
+        gas_limit: Option<u64>,
+        basefee: Option<&Bound<'_, PyAny>>,
+        prevrandao: Option<&str>,
+        difficulty: Option<&Bound<'_, PyAny>>,
+    ) -> Result<()> {
+        let mut config = self.0.block_config();
+        if let Some(id) = chain_id {
+            config.chain_id = id;
+        }
+        if let Some(address) = coinbase {
+            config.coinbase = str_to_address(address)?;
+        }
+        if let Some(limit) = gas_limit {
+            config.gas_limit = limit;
+        }
+        if let Some(fee) = basefee {
+            config.basefee = int_to_u256(fee)?;
+        }
+        if let Some(value) = prevrandao {
+            config.prevrandao = value
+                .parse::<B256>()
+                .map_err(|_| anyhow!("failed to parse prevrandao from string"))?;
+        }
+        if let Some(value) = difficulty {
+            config.difficulty = int_to_u256(value)?;
+        }
+        self.0.set_block_config(config);
+        Ok(())
+    }
+
     /// Create account with an initial balance
     #[pyo3(signature = (address, balance=None))]
-    pub fn create_account(&mut self, address: &str, balance: Option<u128>) -> Result<()> {
+    pub fn create_account(
+        &mut self,
+        address: &str,
+        balance: Option<&Bound<'_, PyAny>>,
+    ) -> Result<()> {
         let caller = str_to_address(address)?;
-        let value = balance.map(U256::from);
+        let value = balance.map(int_to_u256).transpose()?;
         self.0.create_account(caller, value)
     }
 
     /// Get the balance of the given user
-    pub fn get_balance(&mut self, user: &str) -> Result<u128> {
+    pub fn get_balance(&mut self, user: &str, py: Python<'_>) -> Result<PyObject> {
         let user = str_to_address(user)?;
         let v = self.0.get_balance(user)?;
-        Ok(v.to::<u128>())
+        Ok(convert_ints(v.to_le_bytes::<32>(), false, py))
+    }
+
+    /// Impersonate `caller` for all transactions until `stop_prank` is called
+    pub fn start_prank(&mut self, caller: &str) -> Result<()> {
+        let caller = str_to_address(caller)?;
+        self.0.start_prank(caller);
+        Ok(())
+    }
+
+    /// Stop impersonating the caller
+    pub fn stop_prank(&mut self) {
+        self.0.stop_prank()
+    }
+
+    /// Return the address being impersonated, if any
+    pub fn get_prank(&self) -> Option<String> {
+        self.0.prank().map(|address| address.to_string())
+    }
+
+    /// Set the balance of the given account. Other account fields are not changed.
+    pub fn set_balance(&mut self, address: &str, balance: &Bound<'_, PyAny>) -> Result<()> {
+        let address = str_to_address(address)?;
+        self.0.set_balance(address, int_to_u256(balance)?)
+    }
+
+    /// Get the nonce of the given account
+    pub fn get_nonce(&self, address: &str) -> Result<u64> {
+        let address = str_to_address(address)?;
+        self.0.get_nonce(address)
+    }
+
+    /// Set the nonce of the given account. Other account fields are not changed.
+    pub fn set_nonce(&mut self, address: &str, nonce: u64) -> Result<()> {
+        let address = str_to_address(address)?;
+        self.0.set_nonce(address, nonce)
+    }
+
+    /// Get the runtime bytecode of the given account
+    pub fn get_code(&self, address: &str) -> Result<Vec<u8>> {
+        let address = str_to_address(address)?;
+        Ok(self.0.get_code(address)?.to_vec())
+    }
+
+    /// Set (etch) the runtime bytecode of the given account. Other account fields are not changed.
+    pub fn set_code(&mut self, address: &str, code: Vec<u8>) -> Result<()> {
+        let address = str_to_address(address)?;
+        self.0.set_code(address, code.into())
+    }
+
+    /// Get the value of a storage slot of the given account
+    pub fn get_storage_at(
+        &self,
+        address: &str,
+        slot: &Bound<'_, PyAny>,
+        py: Python<'_>,
+    ) -> Result<PyObject> {
+        let address = str_to_address(address)?;
+        let value = self.0.get_storage_at(address, int_to_u256(slot)?)?;
+        Ok(convert_ints(value.to_le_bytes::<32>(), false, py))
+    }
+
+    /// Set the value of a storage slot of the given account
+    pub fn set_storage_at(
+        &mut self,
+        address: &str,
+        slot: &Bound<'_, PyAny>,
+        value: &Bound<'_, PyAny>,
+    ) -> Result<()> {
+        let address = str_to_address(address)?;
+        self.0
+            .set_storage_at(address, int_to_u256(slot)?, int_to_u256(value)?)
+    }
+
+    /// Register a precompile at `address` that always returns `output` and uses `gas_used`.
+    /// Useful to stub an oracle or a precompile of another chain
+    #[pyo3(signature = (address, output, gas_used=0))]
+    pub fn stub_precompile(&mut self, address: &str, output: Vec<u8>, gas_used: u64) -> Result<()> {
+        let address = str_to_address(address)?;
+        self.0.register_precompile(
+            address,
+            StubPrecompile {
+                output: output.into(),
+                gas_used,
+            },
+        );
+        Ok(())
+    }
+
+    /// Register a Python `callback` as a precompile at `address`.  It's called with the
+    /// calldata as bytes and returns the output as bytes, or a tuple of the output and
+    /// the gas used.  Each call uses `gas_used` unless the callback returns the gas.
+    /// An exception reverts the call with the exception as the reason
+    #[pyo3(signature = (address, callback, gas_used=0))]
+    pub fn register_precompile(
+        &mut self,
+        address: &str,
+        callback: PyObject,
+        gas_used: u64,
+    ) -> Result<()> {
+        let address = str_to_address(address)?;
+        self.0
+            .register_precompile(address, PyPrecompile { callback, gas_used });
+        Ok(())
+    }
+
+    /// Remove a custom precompile. Returns False if there wasn't one at `address`
+    pub fn remove_precompile(&mut self, address: &str) -> Result<bool> {
+        let address = str_to_address(address)?;
+        Ok(self.0.remove_precompile(address))
     }
 
     /// Transfer the amount of value from `caller` to the given recipient `to`.
-    pub fn transfer(&mut self, caller: &str, to: &str, amount: u128) -> Result<()> {
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (caller, to, amount, gas_limit=None, gas_price=None, max_fee_per_gas=None, max_priority_fee_per_gas=None))]
+    pub fn transfer(
+        &mut self,
+        caller: &str,
+        to: &str,
+        amount: u128,
+        gas_limit: Option<u64>,
+        gas_price: Option<PyU256>,
+        max_fee_per_gas: Option<PyU256>,
+        max_priority_fee_per_gas: Option<PyU256>,
+        py: Python<'_>,
+    ) -> PyResult<TxResult> {
         let a = str_to_address(caller)?;
         let b = str_to_address(to)?;
-        let value = U256::try_from(amount)?;
-        self.0.transfer(a, b, value)
+        let value = U256::try_from(amount).map_err(anyhow::Error::from)?;
+        let opts = tx_options(
+            gas_limit,
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            vec![],
+        );
+        let output = self
+            .0
+            .transfer(a, b, value, opts)
+            .map_err(|e| execution_error(e, None, py))?;
+        let trace = output
+            .trace
+            .as_ref()
+            .map(|trace| trace_to_dict(trace, py))
+            .transpose()?;
+        Ok(TxResult {
+            output: None,
+            event: None,
+            gas_used: output.gas_used,
+            gas_refunded: output.gas_refunded,
+            effective_gas_price: convert_ints(
+                output.effective_gas_price.to_le_bytes::<32>(),
+                false,
+                py,
+            ),
+            fee: convert_ints(output.fee.to_le_bytes::<32>(), false, py),
+            trace,
+            console_logs: output.console_logs,
+            tx_hash: output.tx_hash.map(|hash| hash.to_string()),
+        })
     }
 
     /// Deploy a contract
-    pub fn deploy(&mut self, args: &str, caller: &str, value: u128, abi: &PyAbi) -> Result<String> {
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (args, caller, value, abi, gas_limit=None, gas_price=None, max_fee_per_gas=None, max_priority_fee_per_gas=None))]
+    pub fn deploy(
+        &mut self,
+        args: &str,
+        caller: &str,
+        value: u128,
+        abi: &PyAbi,
+        gas_limit: Option<u64>,
+        gas_price: Option<PyU256>,
+        max_fee_per_gas: Option<PyU256>,
+        max_priority_fee_per_gas: Option<PyU256>,
+        py: Python<'_>,
+    ) -> PyResult<String> {
         let a = str_to_address(caller)?;
-        let v = U256::try_from(value)?;
+        let v = U256::try_from(value).map_err(anyhow::Error::from)?;
         let (bits, _is_payable) = abi.encode_constructor(args)?;
-        let addy = self.0.deploy(a, bits, v)?;
+        let opts = tx_options(
+            gas_limit,
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            vec![],
+        );
+        let addy = self
+            .0
+            .deploy(a, bits, v, opts)
+            .map_err(|e| execution_error(e, Some(abi), py))?;
         Ok(addy.to_string())
     }
 
+    /// Deploy a contract at `address`.  The constructor is run at `address` by `caller`,
+    /// and the runtime code it returns becomes the code at `address`.
+    pub fn deploy_at(
+        &mut self,
+        address: &str,
+        args: &str,
+        caller: &str,
+        value: u128,
+        abi: &PyAbi,
+        py: Python<'_>,
+    ) -> PyResult<String> {
+        let address = str_to_address(address)?;
+        let caller = str_to_address(caller)?;
+        let v = U256::try_from(value).map_err(anyhow::Error::from)?;
+        let (bits, _is_payable) = abi.encode_constructor(args)?;
+        self.0
+            .deploy_at(caller, address, bits, v)
+            .map_err(|e| execution_error(e, Some(abi), py))?;
+        Ok(address.to_string())
+    }
+
+    /// Deploy a contract with CREATE2 through the deterministic deployment factory.
+    /// The address only depends on the `salt` and the init code.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (args, caller, salt, value, abi, gas_limit=None, gas_price=None, max_fee_per_gas=None, max_priority_fee_per_gas=None))]
+    pub fn deploy_create2(
+        &mut self,
+        args: &str,
+        caller: &str,
+        salt: &str,
+        value: u128,
+        abi: &PyAbi,
+        gas_limit: Option<u64>,
+        gas_price: Option<PyU256>,
+        max_fee_per_gas: Option<PyU256>,
+        max_priority_fee_per_gas: Option<PyU256>,
+        py: Python<'_>,
+    ) -> PyResult<String> {
+        let a = str_to_address(caller)?;
+        let salt = str_to_salt(salt)?;
+        let v = U256::try_from(value).map_err(anyhow::Error::from)?;
+        let (bits, _is_payable) = abi.encode_constructor(args)?;
+        let opts = tx_options(
+            gas_limit,
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            vec![],
+        );
+        let addy = self
+            .0
+            .deploy_create2(a, salt, bits, v, opts)
+            .map_err(|e| execution_error(e, Some(abi), py))?;
+        Ok(addy.to_string())
+    }
+
+    /// Return the address of a contract created with CREATE by `deployer`.  If `nonce`
+    /// is None, the current nonce of the `deployer` is used: the address of its next deployment.
+    #[pyo3(signature = (deployer, nonce=None))]
+    pub fn compute_create_address(&self, deployer: &str, nonce: Option<u64>) -> Result<String> {
+        let deployer = str_to_address(deployer)?;
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => self.0.get_nonce(deployer)?,
+        };
+        Ok(create_address(deployer, nonce).to_string())
+    }
+
+    /// Return the address of a contract created with CREATE2 with `salt` and `init_code`.
+    /// The `deployer` defaults to the deterministic deployment factory used by `deploy_create2`
+    #[pyo3(signature = (salt, init_code, deployer=None))]
+    pub fn compute_create2_address(
+        &self,
+        salt: &str,
+        init_code: Vec<u8>,
+        deployer: Option<&str>,
+    ) -> Result<String> {
+        let deployer = match deployer {
+            Some(deployer) => str_to_address(deployer)?,
+            None => CREATE2_FACTORY,
+        };
+        Ok(create2_address(deployer, str_to_salt(salt)?, &init_code).to_string())
+    }
+
     /// Transaction (write) operation to a contract at the given address `to`. This
     /// will change state in the EVM.
     ///
@@ -102,6 +742,8 @@ impl PyEvm {
     /// Where the event map is:
     /// `key`   is the name of the event
     /// `value` is the decoded log
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (fn_name, args, caller, to, value, abi, gas_limit=None, gas_price=None, max_fee_per_gas=None, max_priority_fee_per_gas=None, access_list=None))]
     pub fn transact(
         &mut self,
         fn_name: &str,
@@ -110,31 +752,54 @@ impl PyEvm {
         to: &str,
         value: u128,
         abi: &PyAbi,
+        gas_limit: Option<u64>,
+        gas_price: Option<PyU256>,
+        max_fee_per_gas: Option<PyU256>,
+        max_priority_fee_per_gas: Option<PyU256>,
+        access_list: Option<Vec<AccessListItem>>,
         py: Python<'_>,
-    ) -> Result<TxResult> {
+    ) -> PyResult<TxResult> {
         let a = str_to_address(caller)?;
         let b = str_to_address(to)?;
-        let v = U256::try_from(value)?;
+        let v = U256::try_from(value).map_err(anyhow::Error::from)?;
         let (calldata, _is_payable, decoder) = abi.encode_function(fn_name, args)?;
-        let output = self.0.transact_commit(a, b, calldata, v)?;
-        process_results_and_events(abi, output, decoder, py)
+        let opts = tx_options(
+            gas_limit,
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            access_list_from_py(access_list)?,
+        );
+        let output = self
+            .0
+            .transact_commit(a, b, calldata, v, opts)
+            .map_err(|e| execution_error(e, Some(abi), py))?;
+        Ok(process_results_and_events(abi, output, decoder, py)?)
     }
 
     /// Transaction (read) operation to a contract at the given address `to`. This
     /// will NOT change state in the EVM.
     ///
+    /// `caller` is optional and used for `msg.sender`.
+    ///
     /// Returns any results of the call
+    #[pyo3(signature = (fn_name, args, to, abi, caller=None))]
     pub fn call(
         &mut self,
         fn_name: &str,
         args: &str,
         to: &str,
         abi: &PyAbi,
+        caller: Option<&str>,
         py: Python<'_>,
-    ) -> Result<Option<PyObject>> {
+    ) -> PyResult<Option<PyObject>> {
         let to_address = str_to_address(to)?;
+        let caller = caller.map(str_to_address).transpose()?;
         let (calldata, _is_payable, decoder) = abi.encode_function(fn_name, args)?;
-        let output = self.0.transact_call(to_address, calldata, U256::from(0))?;
+        let output = self
+            .0
+            .transact_call(to_address, calldata, U256::from(0), caller)
+            .map_err(|e| execution_error(e, Some(abi), py))?;
         let res = process_results(output, decoder, py);
         Ok(res)
     }
@@ -146,6 +811,8 @@ impl PyEvm {
     /// Where the event map is:
     /// `key`   is the name of the event
     /// `value` is the decoded log
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (fn_name, args, caller, to, value, abi, gas_limit=None, gas_price=None, max_fee_per_gas=None, max_priority_fee_per_gas=None, access_list=None))]
     pub fn simulate(
         &mut self,
         fn_name: &str,
@@ -154,14 +821,154 @@ impl PyEvm {
         to: &str,
         value: u128,
         abi: &PyAbi,
+        gas_limit: Option<u64>,
+        gas_price: Option<PyU256>,
+        max_fee_per_gas: Option<PyU256>,
+        max_priority_fee_per_gas: Option<PyU256>,
+        access_list: Option<Vec<AccessListItem>>,
         py: Python<'_>,
-    ) -> Result<TxResult> {
+    ) -> PyResult<TxResult> {
         let caller_address = str_to_address(caller)?;
         let to_address = str_to_address(to)?;
-        let v = U256::try_from(value)?;
+        let v = U256::try_from(value).map_err(anyhow::Error::from)?;
         let (calldata, _is_payable, decoder) = abi.encode_function(fn_name, args)?;
-        let output = self.0.simulate(caller_address, to_address, calldata, v)?;
-        process_results_and_events(abi, output, decoder, py)
+        let opts = tx_options(
+            gas_limit,
+            gas_price,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            access_list_from_py(access_list)?,
+        );
+        let output = self
+            .0
+            .simulate(caller_address, to_address, calldata, v, opts)
+            .map_err(|e| execution_error(e, Some(abi), py))?;
+        Ok(process_results_and_events(abi, output, decoder, py)?)
+    }
+
+    /// Execute a bundle of transactions in order.  Each transaction is a dict with the keys
+    /// `fn_name`, `args`, `caller`, `to`, `abi` and optionally `value` and `gas_limit`.
+    ///
+    /// If `all_or_nothing` is true, the exception of the transaction that failed is raised
+    /// and no state is changed.  Otherwise, failed transactions are skipped and their
+    /// exception is returned in place of the `TxResult`.
+    #[pyo3(signature = (txs, all_or_nothing=true))]
+    pub fn transact_bundle(
+        &mut self,
+        txs: Vec<BundleTx<'_>>,
+        all_or_nothing: bool,
+        py: Python<'_>,
+    ) -> PyResult<Vec<PyObject>> {
+        run_bundle(&mut self.0, txs, all_or_nothing, true, py)
+    }
+
+    /// Same as `transact_bundle` but will NOT change state in the EVM.
+    #[pyo3(signature = (txs, all_or_nothing=true))]
+    pub fn simulate_bundle(
+        &mut self,
+        txs: Vec<BundleTx<'_>>,
+        all_or_nothing: bool,
+        py: Python<'_>,
+    ) -> PyResult<Vec<PyObject>> {
+        run_bundle(&mut self.0, txs, all_or_nothing, false, py)
+    }
+
+    /// Estimate the gas limit needed for a call to a contract to succeed. Does not change state.
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (fn_name, args, caller, to, value, abi))]
+    pub fn estimate_gas(
+        &mut self,
+        fn_name: &str,
+        args: &str,
+        caller: &str,
+        to: &str,
+        value: u128,
+        abi: &PyAbi,
+        py: Python<'_>,
+    ) -> PyResult<u64> {
+        let caller_address = str_to_address(caller)?;
+        let to_address = str_to_address(to)?;
+        let v = U256::try_from(value).map_err(anyhow::Error::from)?;
+        let (calldata, _is_payable, _decoder) = abi.encode_function(fn_name, args)?;
+        self.0
+            .estimate_gas(caller_address, to_address, calldata, v)
+            .map_err(|e| execution_error(e, Some(abi), py))
+    }
+
+    /// Generate an EIP-2930 access list for a call to a contract without changing state.
+    ///
+    /// Returns a dict with the `access_list`, and the gas used with (`gas_used`) and
+    /// without (`gas_used_without_access_list`) it.  If the call fails, `error` is why, and
+    /// the access list has what was accessed until then
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (fn_name, args, caller, to, value, abi))]
+    pub fn create_access_list(
+        &mut self,
+        fn_name: &str,
+        args: &str,
+        caller: &str,
+        to: &str,
+        value: u128,
+        abi: &PyAbi,
+        py: Python<'_>,
+    ) -> PyResult<PyObject> {
+        let caller_address = str_to_address(caller)?;
+        let to_address = str_to_address(to)?;
+        let v = U256::try_from(value).map_err(anyhow::Error::from)?;
+        let (calldata, _is_payable, _decoder) = abi.encode_function(fn_name, args)?;
+        let result = self
+            .0
+            .create_access_list(caller_address, to_address, calldata, v)
+            .map_err(|e| execution_error(e, Some(abi), py))?;
+
+        let dict = PyDict::new(py);
+        dict.set_item("access_list", access_list_to_py(&result.access_list, py)?)?;
+        dict.set_item("gas_used", result.gas_used)?;
+        dict.set_item(
+            "gas_used_without_access_list",
+            result.gas_used_without_access_list,
+        )?;
+        let error = result.error.map(|err| {
+            execution_error(err.into(), Some(abi), py)
+                .value(py)
+                .to_string()
+        });
+        dict.set_item("error", error)?;
+        Ok(dict.into_any().unbind())
+    }
+
+    /// Trace each opcode executed by a call to a contract without changing state.
+    ///
+    /// Returns a dict in the same format as the JSON of geth's `debug_traceTransaction`
+    /// default struct logger
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (fn_name, args, caller, to, value, abi, enable_memory=false, disable_stack=false, disable_storage=false))]
+    pub fn debug_trace_call(
+        &mut self,
+        fn_name: &str,
+        args: &str,
+        caller: &str,
+        to: &str,
+        value: u128,
+        abi: &PyAbi,
+        enable_memory: bool,
+        disable_stack: bool,
+        disable_storage: bool,
+        py: Python<'_>,
+    ) -> Result<PyObject> {
+        let caller_address = str_to_address(caller)?;
+        let to_address = str_to_address(to)?;
+        let v = U256::try_from(value)?;
+        let (calldata, _is_payable, _decoder) = abi.encode_function(fn_name, args)?;
+        let config = StructLoggerConfig {
+            enable_memory,
+            disable_stack,
+            disable_storage,
+        };
+        let trace =
+            self.0
+                .debug_trace_call(caller_address, to_address, calldata, v, None, config)?;
+        struct_log_trace_to_dict(&trace, py)
     }
 
     /// Advance block.number and block.timestamp. Set interval to the amount of
@@ -174,10 +981,451 @@ impl PyEvm {
         let it = interval.unwrap_or(DEFAULT_BLOCK_INTERVAL);
         self.0.update_block(it);
     }
+
+    /// Return `block.number` of the current block
+    pub fn get_block_number(&self) -> u64 {
+        self.0.block_number()
+    }
+
+    /// Return `block.timestamp` of the current block
+    pub fn get_timestamp(&self) -> u64 {
+        self.0.timestamp()
+    }
+
+    /// Set `block.timestamp` of the current block.  It may go back in time.  With
+    /// `interval` mining, the current block is mined first if the interval has passed.
+    pub fn warp(&mut self, timestamp: u64) {
+        self.0.warp(timestamp)
+    }
+
+    /// Set `block.number` of the current block.  It may go back, like `warp`.
+    /// No block is mined.
+    pub fn roll(&mut self, block_number: u64) {
+        self.0.roll(block_number)
+    }
+
+    /// Set when blocks are mined: `manual`, `auto` (a block for each transaction),
+    /// `batch` (a block every `transactions` transactions) or `interval` (a block every
+    /// `interval` seconds of block time, see `warp`).  `auto` with `transactions` is the same as `batch`.
+    /// For `auto` and `batch`, `interval` is the time between blocks (default: 12s).
+    #[pyo3(signature = (mode, interval=None, transactions=None))]
+    pub fn set_mining_mode(
+        &mut self,
+        mode: &str,
+        interval: Option<u64>,
+        transactions: Option<u64>,
+    ) -> Result<()> {
+        if transactions.is_some() && mode != "auto" && mode != "batch" {
+            bail!("transactions is only used with auto or batch mining")
+        }
+        let mode = match (mode, interval) {
+            ("manual", _) => MiningMode::Manual,
+            ("batch", _) if transactions.is_none() => {
+                bail!("batch mining requires a number of transactions")
+            }
+            ("auto" | "batch", _) => match transactions {
+                None | Some(1) => MiningMode::Auto(interval.unwrap_or(DEFAULT_BLOCK_INTERVAL)),
+                Some(0) => bail!("transactions must be greater than 0"),
+                Some(transactions) => MiningMode::Batch {
+                    transactions,
+                    block_time: interval.unwrap_or(DEFAULT_BLOCK_INTERVAL),
+                },
+            },
+            ("interval", Some(interval)) => MiningMode::Interval(interval),
+            ("interval", None) => bail!("interval mining requires an interval"),
+            _ => bail!("unknown mining mode: {}", mode),
+        };
+        self.0.set_mining_mode(mode);
+        Ok(())
+    }
+
+    /// Return the mining mode as a dict with the keys `mode`, `interval` and
+    /// `transactions`, the arguments of `set_mining_mode`
+    pub fn get_mining_mode(&self, py: Python<'_>) -> Result<PyObject> {
+        let (mode, interval, transactions) = match self.0.mining_mode() {
+            MiningMode::Manual => ("manual", None, None),
+            MiningMode::Auto(interval) => ("auto", Some(interval), None),
+            MiningMode::Batch {
+                transactions,
+                block_time,
+            } => ("batch", Some(block_time), Some(transactions)),
+            MiningMode::Interval(interval) => ("interval", Some(interval), None),
+        };
+        let dict = PyDict::new(py);
+        dict.set_item("mode", mode)?;
+        dict.set_item("interval", interval)?;
+        dict.set_item("transactions", transactions)?;
+        Ok(dict.into_any().unbind())
+    }
+
+    /// Set the order pending transactions are mined in: `fifo` or `priority_fee`
+    pub fn set_tx_ordering(&mut self, ordering: &str) -> Result<()> {
+        let ordering = match ordering {
+            "fifo" => TxOrdering::Fifo,
+            "priority_fee" => TxOrdering::PriorityFee,
+            _ => bail!("unknown transaction ordering: {}", ordering),
+        };
+        self.0.set_tx_ordering(ordering);
+        Ok(())
+    }
+
+    /// Queue a transaction to a contract in the mempool.  It's executed when the next
+    /// block is mined.  Returns the transaction hash
+    #[allow(clippy::too_many_arguments)]
+    #[pyo3(signature = (fn_name, args, caller, to, value, abi, gas_limit=None, gas_price=None, max_fee_per_gas=None, max_priority_fee_per_gas=None))]
+    pub fn send_transaction(
+        &mut self,
+        fn_name: &str,
+        args: &str,
+        caller: &str,
+        to: &str,
+        value: u128,
+        abi: &PyAbi,
+        gas_limit: Option<u64>,
+        gas_price: Option<PyU256>,
+        max_fee_per_gas: Option<PyU256>,
+        max_priority_fee_per_gas: Option<PyU256>,
+    ) -> Result<String> {
+        let (data, _is_payable, _decoder) = abi.encode_function(fn_name, args)?;
+        let tx = TxRequest {
+            caller: str_to_address(caller)?,
+            to: Some(str_to_address(to)?),
+            data,
+            value: U256::try_from(value)?,
+            opts: tx_options(
+                gas_limit,
+                gas_price,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                AccessList::default(),
+            ),
+        };
+        Ok(self.0.send_transaction(tx)?.to_string())
+    }
+
+    /// Return the hashes of the transactions waiting to be mined
+    pub fn pending_transactions(&self) -> Vec<String> {
+        self.0
+            .pending_transactions()
+            .iter()
+            .map(B256::to_string)
+            .collect()
+    }
+
+    /// Mine the pending transactions into a block and start a new block `interval`
+    /// seconds later (default: 12s).  Returns the block header as a dict, with the
+    /// invalid transactions that were dropped in `dropped`
+    #[pyo3(signature = (interval=None))]
+    pub fn mine_block(&mut self, interval: Option<u64>, py: Python<'_>) -> Result<PyObject> {
+        let (block, dropped) = self
+            .0
+            .mine_block(interval.unwrap_or(DEFAULT_BLOCK_INTERVAL));
+        let dict = block_to_dict(&block.header, &block.transactions, py)?;
+        let dropped = dropped
+            .iter()
+            .map(|tx| {
+                let item = PyDict::new(py);
+                item.set_item("hash", tx.hash.to_string())?;
+                item.set_item("error", tx.error.to_string())?;
+                Ok(item)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        dict.bind(py).set_item("dropped", dropped)?;
+        Ok(dict)
+    }
+
+    /// Return a committed transaction by `hash`, or None
+    pub fn get_transaction(&self, hash: &str, py: Python<'_>) -> Result<Option<PyObject>> {
+        let hash = hash.parse::<B256>()?;
+        self.0
+            .get_transaction(hash)
+            .map(|tx| transaction_to_dict(tx, py))
+            .transpose()
+    }
+
+    /// Return the receipt of a committed transaction by `hash`, or None
+    pub fn get_receipt(&self, hash: &str, py: Python<'_>) -> Result<Option<PyObject>> {
+        let hash = hash.parse::<B256>()?;
+        self.0
+            .get_receipt(hash)
+            .map(|receipt| receipt_to_dict(receipt, py))
+            .transpose()
+    }
+
+    /// Return a mined block by `number`, or None
+    pub fn get_block(&self, number: u64, py: Python<'_>) -> Result<Option<PyObject>> {
+        self.0
+            .get_block(number)
+            .map(|block| block_to_dict(&block.header, &block.transactions, py))
+            .transpose()
+    }
+
+    /// Return the logs emitted by committed transactions that match the filter.
+    /// If `abi` is given, each log is decoded as an event of the contract
+    #[pyo3(signature = (address=None, topics=None, from_block=None, to_block=None, abi=None))]
+    pub fn get_logs(
+        &self,
+        address: Option<OneOrMany>,
+        topics: Option<Vec<Option<OneOrMany>>>,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+        abi: Option<&PyAbi>,
+        py: Python<'_>,
+    ) -> Result<Vec<PyObject>> {
+        let addresses = address
+            .map(OneOrMany::into_vec)
+            .unwrap_or_default()
+            .iter()
+            .map(|address| str_to_address(address))
+            .collect::<Result<Vec<_>>>()?;
+        let topics = topics
+            .unwrap_or_default()
+            .into_iter()
+            .map(|topic| {
+                topic
+                    .map(|values| {
+                        values
+                            .into_vec()
+                            .iter()
+                            .map(|value| str_to_topic(value))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let filter = LogFilter {
+            from_block,
+            to_block,
+            addresses,
+            topics,
+        };
+
+        self.0
+            .get_logs(&filter)
+            .into_iter()
+            .map(|log| {
+                let dict = log_to_dict(&log.log, py)?;
+                dict.set_item("block_number", log.block_number)?;
+                dict.set_item("transaction_hash", log.transaction_hash.to_string())?;
+                dict.set_item("transaction_index", log.transaction_index)?;
+                dict.set_item("log_index", log.log_index)?;
+                if let Some(abi) = abi {
+                    let (event, args) = match log.decode(&abi.0) {
+                        Some((name, values)) => {
+                            (Some(name), Some(DynSolMap(values).into_py_any(py)?))
+                        }
+                        None => (None, None),
+                    };
+                    dict.set_item("event", event)?;
+                    dict.set_item("args", args)?;
+                }
+                Ok(dict.into_any().unbind())
+            })
+            .collect()
+    }
 }
 
 // *** lil' Helpers *** //
 
+// A precompile implemented by a Python function. See `PyEvm.register_precompile`
+struct PyPrecompile {
+    callback: PyObject,
+    gas_used: u64,
+}
+
+// The output of the callback, with or without the gas used
+#[derive(FromPyObject)]
+enum PrecompileReturn {
+    WithGas(Vec<u8>, u64),
+    Output(Vec<u8>),
+}
+
+impl CustomPrecompile for PyPrecompile {
+    fn call(&self, input: &Bytes, gas_limit: u64) -> CustomPrecompileResult {
+        Python::with_gil(|py| {
+            let returned = self
+                .callback
+                .call1(py, (PyBytes::new(py, input),))
+                .and_then(|returned| returned.extract::<PrecompileReturn>(py));
+            let (output, gas_used) = match returned {
+                Ok(PrecompileReturn::WithGas(output, gas_used)) => (output, gas_used),
+                Ok(PrecompileReturn::Output(output)) => (output, self.gas_used),
+                Err(err) => {
+                    return Err(CustomPrecompileError::Revert {
+                        output: Revert::from(err.to_string()).abi_encode().into(),
+                        gas_used: self.gas_used.min(gas_limit),
+                    })
+                }
+            };
+            if gas_used > gas_limit {
+                return Err(PrecompileError::OutOfGas.into());
+            }
+            Ok((gas_used, output.into()))
+        })
+    }
+}
+
+// Parse a log topic.  An address is left padded to 32 bytes, as it is when indexed
+fn str_to_topic(value: &str) -> Result<B256> {
+    if let Ok(address) = value.parse::<Address>() {
+        return Ok(address.into_word());
+    }
+    value
+        .parse::<B256>()
+        .map_err(|_| anyhow!("failed to parse topic from string: {}", value))
+}
+
+// Parse a CREATE2 salt: 32 bytes of hex
+fn str_to_salt(value: &str) -> Result<B256> {
+    value
+        .parse::<B256>()
+        .map_err(|_| anyhow!("failed to parse salt from string: {}", value))
+}
+
+// Raise a `RevertError` if the transaction reverted, or an `OutOfGasError`.  Custom
+// errors are decoded with `abi`.  Other errors are converted as usual.
+fn execution_error(err: anyhow::Error, abi: Option<&PyAbi>, py: Python<'_>) -> PyErr {
+    let Some(execution) = err.downcast_ref::<ExecutionError>() else {
+        return err.into();
+    };
+    let (data, gas_used) = match execution {
+        ExecutionError::Revert { data, gas_used } => (data, gas_used),
+        ExecutionError::OutOfGas {
+            gas_limit,
+            gas_used,
+        } => return out_of_gas_error(execution.to_string(), *gas_limit, *gas_used, py),
+        _ => return err.into(),
+    };
+    let decoded = match abi {
+        Some(abi) => abi.0.decode_error(data),
+        None => decode_builtin_error(data),
+    };
+    let message = match &decoded {
+        Some((name, _)) if name != "Error" && name != "Panic" => {
+            format!("Reverted: {}. Gas used: {}", name, gas_used)
+        }
+        _ => execution.to_string(),
+    };
+    let (name, values) = decoded.unzip();
+
+    let exception = RevertError::new_err(message);
+    let attrs = || -> PyResult<()> {
+        let value = exception.value(py);
+        value.setattr("name", name)?;
+        value.setattr(
+            "error_args",
+            values.map(|values| walk_tuple(values, py)).transpose()?,
+        )?;
+        value.setattr("data", PyBytes::new(py, data))?;
+        value.setattr("gas_used", *gas_used)?;
+        Ok(())
+    };
+    match attrs() {
+        Ok(()) => exception,
+        Err(e) => e,
+    }
+}
+
+// Run a bundle with `BaseEvm::transact_bundle`, or `simulate_bundle` if not `commit`.
+// Results and errors are decoded with the ABI of each transaction.
+fn run_bundle(
+    evm: &mut BaseEvm,
+    txs: Vec<BundleTx<'_>>,
+    all_or_nothing: bool,
+    commit: bool,
+    py: Python<'_>,
+) -> PyResult<Vec<PyObject>> {
+    let mut requests = Vec::with_capacity(txs.len());
+    let mut decoders = Vec::with_capacity(txs.len());
+    for tx in &txs {
+        let (data, _is_payable, decoder) = tx.abi.encode_function(&tx.fn_name, &tx.args)?;
+        requests.push(TxRequest {
+            caller: str_to_address(&tx.caller)?,
+            to: Some(str_to_address(&tx.to)?),
+            data,
+            value: U256::try_from(tx.value).map_err(anyhow::Error::from)?,
+            opts: tx_options(tx.gas_limit, None, None, None, AccessList::default()),
+        });
+        decoders.push(decoder);
+    }
+
+    let results = match commit {
+        true => evm.transact_bundle(requests, all_or_nothing),
+        false => evm.simulate_bundle(requests, all_or_nothing),
+    }
+    .map_err(|e| {
+        let failed = e.downcast_ref::<BundleError>().map(|b| b.index);
+        execution_error(e, failed.map(|index| &*txs[index].abi), py)
+    })?;
+
+    results
+        .into_iter()
+        .zip(decoders)
+        .zip(&txs)
+        .map(|((result, decoder), tx)| match result {
+            Ok(output) => process_results_and_events(&tx.abi, output, decoder, py)?.into_py_any(py),
+            Err(e) => Ok(execution_error(e, Some(&tx.abi), py)
+                .into_value(py)
+                .into_any()),
+        })
+        .collect()
+}
+
+// Convert a Python int (up to 256 bits) to a U256
+fn int_to_u256(value: &Bound<'_, PyAny>) -> Result<U256> {
+    let bytes: Vec<u8> = value.call_method1("to_bytes", (32, "big"))?.extract()?;
+    Ok(U256::from_be_slice(&bytes))
+}
+
+fn tx_options(
+    gas_limit: Option<u64>,
+    gas_price: Option<PyU256>,
+    max_fee_per_gas: Option<PyU256>,
+    max_priority_fee_per_gas: Option<PyU256>,
+    access_list: AccessList,
+) -> Option<TxOptions> {
+    Some(TxOptions {
+        gas_limit,
+        gas_price: gas_price.map(|price| price.0),
+        max_fee_per_gas: max_fee_per_gas.map(|fee| fee.0),
+        max_priority_fee_per_gas: max_priority_fee_per_gas.map(|fee| fee.0),
+        access_list,
+    })
+}
+
+// Convert an access list from Python: a list of dicts with an `address` and
+// hex encoded `storage_keys`
+fn access_list_from_py(items: Option<Vec<AccessListItem>>) -> Result<AccessList> {
+    items
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| {
+            let keys = item
+                .storage_keys
+                .iter()
+                .map(|key| Ok(U256::from_be_bytes(key.parse::<B256>()?.0)))
+                .collect::<Result<Vec<_>>>()?;
+            Ok((str_to_address(&item.address)?, keys))
+        })
+        .collect()
+}
+
+fn access_list_to_py(access_list: &AccessList, py: Python<'_>) -> Result<PyObject> {
+    let mut items = vec![];
+    for (address, keys) in access_list {
+        let dict = PyDict::new(py);
+        dict.set_item("address", address.to_string())?;
+        dict.set_item(
+            "storage_keys",
+            keys.iter()
+                .map(|key| B256::from(*key).to_string())
+                .collect::<Vec<_>>(),
+        )?;
+        items.push(dict);
+    }
+    Ok(items.into_py_any(py)?)
+}
+
 fn process_results(
     output: CallResult,
     decoder: DynSolTypeWrapper,
@@ -200,13 +1448,27 @@ fn process_results_and_events(
     py: Python<'_>,
 ) -> Result<TxResult> {
     let logs = output_result.logs.clone();
-    let gas_used = output_result.gas_used.clone();
+    let gas_used = output_result.gas_used;
+    let gas_refunded = output_result.gas_refunded;
+    let effective_gas_price = convert_ints(
+        output_result.effective_gas_price.to_le_bytes::<32>(),
+        false,
+        py,
+    );
+    let fee = convert_ints(output_result.fee.to_le_bytes::<32>(), false, py);
+    let tx_hash = output_result.tx_hash.map(|hash| hash.to_string());
+    let console_logs = output_result.console_logs.clone();
+    let trace = output_result
+        .trace
+        .as_ref()
+        .map(|trace| trace_to_dict(trace, py))
+        .transpose()?;
 
     // process return value
     let output = process_results(output_result, decoder, py);
 
     // process logs
-    let event = if logs.len() > 0 {
+    let event = if !logs.is_empty() {
         let raw_events = abi.0.extract_logs(logs);
         let mut map = HashMap::<String, PyObject>::new();
         for (k, v) in raw_events {
@@ -221,9 +1483,216 @@ fn process_results_and_events(
         output,
         event,
         gas_used,
+        gas_refunded,
+        effective_gas_price,
+        fee,
+        trace,
+        console_logs,
+        tx_hash,
     })
 }
 
+// Convert a block header and the hashes of its transactions to a dict
+fn block_to_dict(header: &BlockHeader, transactions: &[B256], py: Python<'_>) -> Result<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("number", header.number)?;
+    dict.set_item("hash", header.hash().to_string())?;
+    dict.set_item("parent_hash", header.parent_hash.to_string())?;
+    dict.set_item("timestamp", header.timestamp)?;
+    dict.set_item("gas_used", header.gas_used)?;
+    dict.set_item("gas_limit", header.gas_limit)?;
+    dict.set_item(
+        "base_fee",
+        convert_ints(header.base_fee.to_le_bytes::<32>(), false, py),
+    )?;
+    let hashes = transactions.iter().map(B256::to_string).collect::<Vec<_>>();
+    dict.set_item("transactions", hashes)?;
+    Ok(dict.into_any().unbind())
+}
+
+fn transaction_to_dict(tx: &Transaction, py: Python<'_>) -> Result<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("hash", tx.hash.to_string())?;
+    dict.set_item("from", tx.from.to_string())?;
+    dict.set_item("to", tx.to.map(|to| to.to_string()))?;
+    dict.set_item(
+        "value",
+        convert_ints(tx.value.to_le_bytes::<32>(), false, py),
+    )?;
+    dict.set_item("input", tx.input.to_string())?;
+    dict.set_item("gas_limit", tx.gas_limit)?;
+    dict.set_item("block_number", tx.block_number)?;
+    dict.set_item("transaction_index", tx.transaction_index)?;
+    Ok(dict.into_any().unbind())
+}
+
+// Convert the `address`, `topics` and `data` of a log to a dict
+fn log_to_dict<'py>(log: &Log, py: Python<'py>) -> Result<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    dict.set_item("address", log.address.to_string())?;
+    let topics = log.topics().iter().map(B256::to_string).collect::<Vec<_>>();
+    dict.set_item("topics", topics)?;
+    dict.set_item("data", log.data.data.to_string())?;
+    Ok(dict)
+}
+
+fn receipt_to_dict(receipt: &Receipt, py: Python<'_>) -> Result<PyObject> {
+    let logs = receipt
+        .logs
+        .iter()
+        .map(|entry| {
+            let log = log_to_dict(&entry.log, py)?;
+            log.set_item("log_index", entry.log_index)?;
+            Ok(log)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("transaction_hash", receipt.transaction_hash.to_string())?;
+    dict.set_item("block_number", receipt.block_number)?;
+    dict.set_item("transaction_index", receipt.transaction_index)?;
+    dict.set_item("status", receipt.status)?;
+    dict.set_item("gas_used", receipt.gas_used)?;
+    dict.set_item("cumulative_gas_used", receipt.cumulative_gas_used)?;
+    dict.set_item(
+        "effective_gas_price",
+        convert_ints(receipt.effective_gas_price.to_le_bytes::<32>(), false, py),
+    )?;
+    dict.set_item(
+        "contract_address",
+        receipt.contract_address.map(|addr| addr.to_string()),
+    )?;
+    dict.set_item("logs", logs)?;
+    dict.set_item("logs_bloom", receipt.logs_bloom.to_string())?;
+    Ok(dict.into_any().unbind())
+}
+
+// Convert the gas report to a dict of `functions`, `deployments` and `opcodes`
+fn gas_report_to_dict(
+    report: &GasReport,
+    abis: &HashMap<Address, PyRef<PyAbi>>,
+    py: Python<'_>,
+) -> Result<PyObject> {
+    let mut functions = vec![];
+    for ((address, selector), stats) in &report.functions {
+        let name = selector.and_then(|selector| {
+            abis.get(address)
+                .and_then(|abi| abi.0.function_by_selector(selector.as_slice()))
+                .map(|f| f.name.clone())
+        });
+        let dict = PyDict::new(py);
+        dict.set_item("address", address.to_string())?;
+        dict.set_item("selector", selector.map(|s| s.to_string()))?;
+        dict.set_item("name", name)?;
+        dict.set_item("calls", stats.calls())?;
+        dict.set_item("min", stats.min())?;
+        dict.set_item("avg", stats.avg())?;
+        dict.set_item("median", stats.median())?;
+        dict.set_item("max", stats.max())?;
+        dict.set_item("total", stats.total())?;
+        functions.push(dict);
+    }
+
+    let mut deployments = vec![];
+    for (address, deployment) in &report.deployments {
+        let dict = PyDict::new(py);
+        dict.set_item("address", address.to_string())?;
+        dict.set_item("gas", deployment.gas)?;
+        dict.set_item("size", deployment.size)?;
+        deployments.push(dict);
+    }
+
+    let opcodes = PyDict::new(py);
+    for (category, used) in &report.opcodes {
+        let dict = PyDict::new(py);
+        dict.set_item("count", used.count)?;
+        dict.set_item("gas", used.gas)?;
+        opcodes.set_item(category.as_str(), dict)?;
+    }
+
+    let dict = PyDict::new(py);
+    dict.set_item("functions", functions)?;
+    dict.set_item("deployments", deployments)?;
+    dict.set_item("opcodes", opcodes)?;
+    Ok(dict.into_any().unbind())
+}
+
+// Convert the call tree to nested dicts
+fn trace_to_dict(trace: &CallTrace, py: Python<'_>) -> Result<PyObject> {
+    let dict = PyDict::new(py);
+    dict.set_item("kind", trace.kind.as_str())?;
+    dict.set_item("from", trace.from.to_string())?;
+    dict.set_item("to", trace.to.to_string())?;
+    dict.set_item(
+        "value",
+        convert_ints(trace.value.to_le_bytes::<32>(), false, py),
+    )?;
+    dict.set_item("input", trace.input.to_string())?;
+    dict.set_item("output", trace.output.to_string())?;
+    dict.set_item("gas_limit", trace.gas_limit)?;
+    dict.set_item("gas_used", trace.gas_used)?;
+    dict.set_item("success", trace.success)?;
+    dict.set_item("error", trace.error.clone())?;
+    let calls = trace
+        .calls
+        .iter()
+        .map(|call| trace_to_dict(call, py))
+        .collect::<Result<Vec<_>>>()?;
+    dict.set_item("calls", calls)?;
+    Ok(dict.into_any().unbind())
+}
+
+// Convert an opcode trace to a dict with the same keys as geth's struct logger JSON
+fn struct_log_trace_to_dict(trace: &StructLogTrace, py: Python<'_>) -> Result<PyObject> {
+    let mut logs = vec![];
+    for log in &trace.struct_logs {
+        let dict = PyDict::new(py);
+        dict.set_item("pc", log.pc)?;
+        dict.set_item("op", &log.op)?;
+        dict.set_item("gas", log.gas)?;
+        dict.set_item("gasCost", log.gas_cost)?;
+        dict.set_item("depth", log.depth)?;
+        if let Some(stack) = &log.stack {
+            dict.set_item("stack", stack)?;
+        }
+        if let Some(memory) = &log.memory {
+            dict.set_item("memory", memory)?;
+        }
+        if let Some(storage) = &log.storage {
+            dict.set_item("storage", storage.clone().into_py_any(py)?)?;
+        }
+        if log.refund > 0 {
+            dict.set_item("refund", log.refund)?;
+        }
+        if let Some(error) = &log.error {
+            dict.set_item("error", error)?;
+        }
+        logs.push(dict);
+    }
+    let dict = PyDict::new(py);
+    dict.set_item("gas", trace.gas)?;
+    dict.set_item("failed", trace.failed)?;
+    dict.set_item("returnValue", &trace.return_value)?;
+    dict.set_item("structLogs", logs)?;
+    Ok(dict.into_any().unbind())
+}
+
+fn out_of_gas_error(message: String, gas_limit: u64, gas_used: u64, py: Python<'_>) -> PyErr {
+    let exception = OutOfGasError::new_err(message);
+    let value = exception.value(py);
+    let attrs = value
+        .setattr("gas_limit", gas_limit)
+        .and_then(|_| value.setattr("gas_used", gas_used));
+    match attrs {
+        Ok(()) => exception,
+        Err(e) => e,
+    }
+}
+
+fn walk_tuple(values: Vec<DynSolValue>, py: Python<'_>) -> PyResult<Bound<'_, PyTuple>> {
+    PyTuple::new(py, values.into_iter().map(|dv| base_exctract(dv, py)))
+}
+
 fn walk_list(values: Vec<DynSolValue>, py: Python<'_>) -> PyObject {
     values
         .into_iter()
@@ -7,6 +7,10 @@ from simular import (
     PyEvm,
     contract_from_inline_abi,
     ether_to_wei,
+    format_gas_report,
+    RevertError,
+    OutOfGasError,
+    TxResult,
 )
 
 
@@ -79,3 +83,490 @@ def test_deploy_and_test_kitchensink(evm, alice, kitchen_sink_json):
     evm.transfer(alice, a.address, one_ether)
     assert one_ether == evm.get_balance(alice)
     assert one_ether == evm.get_balance(a.address)
+
+
+def test_caller_and_prank(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+
+    assert erc20.owner.call(caller=alice) == bob
+
+    # bob is the owner, so anyone can mint while impersonating bob
+    evm.start_prank(bob)
+    assert evm.get_prank().lower() == bob
+    erc20.mint.transact(alice, 10)
+    erc20.mint.transact(alice, 10, caller=alice)
+    evm.stop_prank()
+
+    assert evm.get_prank() is None
+    assert 20 == erc20.balanceOf.call(alice)
+
+    with pytest.raises(BaseException):
+        erc20.mint.transact(alice, 10)
+
+
+def test_call_tracing(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+
+    assert not evm.get_call_tracing()
+    assert erc20.mint.transact(alice, 10, caller=bob).trace is None
+
+    evm.set_call_tracing(True)
+    trace = erc20.mint.transact(alice, 10, caller=bob).trace
+    assert trace["kind"] == "CALL"
+    assert trace["from"].lower() == bob
+    assert trace["to"] == erc20.address
+    assert trace["success"]
+    assert trace["error"] is None
+    assert trace["gas_used"] > 0
+    assert trace["calls"] == []
+    assert trace["input"].startswith("0x")
+
+
+def test_debug_trace(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+
+    trace = erc20.mint.debug_trace(alice, 10, caller=bob)
+    assert not trace["failed"]
+    assert trace["gas"] > 0
+    first = trace["structLogs"][0]
+    assert first["pc"] == 0
+    assert first["depth"] == 1
+    assert first["gasCost"] > 0
+    assert "" == trace["returnValue"]
+    assert "stack" in first
+    assert "memory" not in first
+    assert any(log["op"] == "SSTORE" and log["storage"] for log in trace["structLogs"])
+
+    # state is not changed
+    assert 0 == erc20.balanceOf.call(alice)
+
+    # a revert is traced
+    assert erc20.mint.debug_trace(alice, 10, caller=alice)["failed"]
+
+
+def test_gas_report(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+
+    evm.set_gas_profiling(True)
+    assert evm.get_gas_profiling()
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    erc20.mint.transact(alice, 10, caller=bob)
+    erc20.mint.transact(alice, 20, caller=bob)
+    erc20.balanceOf.call(alice)
+
+    report = evm.get_gas_report({erc20.address: erc20.abi})
+    assert report["deployments"][0]["address"] == erc20.address
+    assert report["deployments"][0]["size"] > 0
+
+    mint = next(f for f in report["functions"] if f["name"] == "mint")
+    assert mint["calls"] == 2
+    assert mint["min"] <= mint["median"] <= mint["max"]
+    assert mint["total"] == mint["min"] + mint["max"]
+    assert report["opcodes"]["SSTORE"]["count"] > 0
+    assert report["opcodes"]["SLOAD"]["gas"] > 0
+
+    # names are only resolved with the abi
+    names = [f["name"] for f in evm.get_gas_report()["functions"]]
+    assert names == [None, None]
+    assert "mint" in format_gas_report(report)
+
+    evm.reset_gas_report()
+    assert evm.get_gas_report()["functions"] == []
+
+
+def test_revert_error(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    address = create_account(evm)
+    # revert with InsufficientBalance(10, 20)
+    evm.set_code(
+        address,
+        bytes.fromhex("63cf47918160e01b5f52600a600452601460245260445ffd"),
+    )
+    c = contract_from_inline_abi(
+        evm, ["function boom()", "error InsufficientBalance(uint256,uint256)"]
+    )
+    c.at(address)
+
+    with pytest.raises(RevertError) as exc:
+        c.boom.transact(caller=bob)
+    assert exc.value.name == "InsufficientBalance"
+    assert exc.value.error_args == (10, 20)
+    assert exc.value.data[:4] == bytes.fromhex("cf479181")
+    assert len(exc.value.data) == 68
+    assert exc.value.gas_used > 0
+
+    with pytest.raises(RevertError) as exc:
+        c.boom.call()
+    assert exc.value.name == "InsufficientBalance"
+
+    # require(..., "reason")
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    with pytest.raises(RevertError) as exc:
+        erc20.mint.transact(alice, 10, caller=alice)
+    assert exc.value.name == "Error"
+    assert exc.value.error_args == ("not the owner",)
+    assert "not the owner" in str(exc.value)
+
+
+def test_access_list(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    erc20.mint.transact(bob, 10, caller=bob)
+
+    result = erc20.transfer.create_access_list(alice, 1, caller=bob)
+    # only the token's storage is accessed
+    assert len(result["access_list"]) == 1
+    item = result["access_list"][0]
+    assert item["address"] == erc20.address
+    assert len(item["storage_keys"]) == 2
+    assert all(len(key) == 66 for key in item["storage_keys"])
+    assert result["gas_used"] > 0
+    assert result["gas_used_without_access_list"] > 0
+
+    # state is not changed
+    assert 0 == erc20.balanceOf.call(alice)
+
+    tx = erc20.transfer.transact(
+        alice, 1, caller=bob, access_list=result["access_list"]
+    )
+    assert tx.gas_used == result["gas_used"]
+    assert 1 == erc20.balanceOf.call(alice)
+    assert result["error"] is None
+
+    # the balance is read before the transfer reverts
+    result = erc20.transfer.create_access_list(bob, 100, caller=alice)
+    assert result["error"] is not None
+    assert erc20.address == result["access_list"][0]["address"]
+    assert len(result["access_list"][0]["storage_keys"]) > 0
+
+
+def test_estimate_gas(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    erc20.mint.transact(bob, 10, caller=bob)
+
+    estimate = erc20.transfer.estimate_gas(alice, 1, caller=bob)
+    assert estimate >= erc20.transfer.simulate(alice, 1, caller=bob).gas_used
+    # state is not changed
+    assert 0 == erc20.balanceOf.call(alice)
+
+    with pytest.raises(RevertError):
+        erc20.mint.estimate_gas(alice, 10, caller=alice)
+
+
+def test_gas_limit(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+
+    with pytest.raises(OutOfGasError) as exc:
+        erc20.mint.transact(bob, 10, caller=bob, gas_limit=30_000)
+    assert 30_000 == exc.value.gas_limit
+    assert 30_000 == exc.value.gas_used
+    assert 0 == erc20.balanceOf.call(bob)
+
+    erc20.mint.transact(bob, 10, caller=bob, gas_limit=100_000)
+    assert 10 == erc20.balanceOf.call(bob)
+
+    # the block gas limit is only enforced when enabled
+    evm.set_block_config(gas_limit=50_000)
+    erc20.transfer.transact(alice, 1, caller=bob, gas_limit=60_000)
+    evm.set_enforce_block_gas_limit(True)
+    assert evm.get_enforce_block_gas_limit()
+    with pytest.raises(Exception):
+        erc20.transfer.transact(alice, 1, caller=bob, gas_limit=60_000)
+    assert 1 == erc20.balanceOf.call(alice)
+
+
+def test_bundles(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, 0)
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+
+    bundle = [
+        erc20.mint.build_transaction(bob, 10, caller=bob),
+        erc20.transfer.build_transaction(alice, 10, caller=bob),
+        erc20.mint.build_transaction(alice, 10, caller=alice),
+    ]
+
+    # nothing is committed
+    with pytest.raises(RevertError) as exc:
+        evm.transact_bundle(bundle)
+    assert "Error" == exc.value.name
+    assert 0 == erc20.balanceOf.call(alice)
+
+    results = evm.simulate_bundle(bundle, all_or_nothing=False)
+    assert 3 == len(results)
+    assert isinstance(results[1], TxResult)
+    assert isinstance(results[2], RevertError)
+    assert 0 == erc20.balanceOf.call(alice)
+
+    results = evm.transact_bundle(bundle[:2])
+    assert results[1].output
+    assert 10 == erc20.balanceOf.call(alice)
+
+
+def test_mining(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, int(1e18))
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    erc20.mint.transact(bob, 10, caller=bob)
+
+    evm.set_tx_ordering("priority_fee")
+    low = erc20.transfer.send(alice, 1, caller=bob, max_priority_fee_per_gas=1)
+    high = erc20.transfer.send(alice, 2, caller=bob, max_priority_fee_per_gas=2)
+    assert [low, high] == evm.pending_transactions()
+    assert 0 == erc20.balanceOf.call(alice)
+
+    block = evm.mine_block()
+    # the block also has the deploy and mint
+    assert [high, low] == block["transactions"][-2:]
+    assert block["gas_used"] > 0
+    assert 3 == erc20.balanceOf.call(alice)
+    assert [] == evm.pending_transactions()
+
+    evm.set_mining_mode("auto", interval=1)
+    assert {"mode": "auto", "interval": 1, "transactions": None} == evm.get_mining_mode()
+    erc20.transfer.send(alice, 1, caller=bob)
+    assert 4 == erc20.balanceOf.call(alice)
+
+    next_block = evm.mine_block()
+    assert block["hash"] != next_block["parent_hash"]
+    assert block["number"] + 2 == next_block["number"]
+    assert block["timestamp"] + 13 == next_block["timestamp"]
+
+    # time only moves with warp
+    evm.set_mining_mode("interval", interval=10)
+    pending = erc20.transfer.send(alice, 1, caller=bob)
+    evm.warp(evm.get_timestamp() + 9)
+    assert [pending] == evm.pending_transactions()
+    evm.warp(evm.get_timestamp() + 1)
+    assert [] == evm.pending_transactions()
+    assert [pending] == evm.get_block(next_block["number"] + 1)["transactions"]
+
+
+def test_warp_roll_and_batch_mining(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, int(1e18))
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    start = evm.get_block_number()
+
+    evm.warp(1_000)
+    evm.roll(100)
+    assert 1_000 == evm.get_timestamp()
+    assert 100 == evm.get_block_number()
+    # the deploy moved to the new block number
+    assert evm.get_block(start) is None
+    evm.mine_block()
+    assert 1 == len(evm.get_block(100)["transactions"])
+    # back to block 100
+    evm.roll(100)
+    evm.warp(1_000)
+
+    evm.set_mining_mode("auto", interval=10, transactions=2)
+    mode = evm.get_mining_mode()
+    assert {"mode": "batch", "interval": 10, "transactions": 2} == mode
+    evm.set_mining_mode(**mode)
+    assert mode == evm.get_mining_mode()
+    tx = erc20.mint.transact(alice, 1, caller=bob)
+    assert 100 == evm.get_block_number()
+    erc20.mint.transact(alice, 1, caller=bob)
+    assert 101 == evm.get_block_number()
+    assert 1_010 == evm.get_timestamp()
+    assert 100 == evm.get_receipt(tx.tx_hash)["block_number"]
+
+    with pytest.raises(Exception):
+        evm.set_mining_mode("auto", transactions=0)
+
+
+def test_receipts(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, int(1e18))
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+
+    tx = erc20.mint.transact(alice, 10, caller=bob)
+    receipt = evm.get_receipt(tx.tx_hash)
+    assert receipt["status"]
+    assert tx.gas_used == receipt["gas_used"]
+    assert 1 == len(receipt["logs"])
+    assert erc20.address.lower() == receipt["logs"][0]["address"].lower()
+    transaction = evm.get_transaction(tx.tx_hash)
+    assert erc20.address.lower() == transaction["to"].lower()
+    assert bob.lower() == transaction["from"].lower()
+
+    block = evm.mine_block()
+    assert tx.tx_hash in block["transactions"]
+    assert [] == block.pop("dropped")
+    assert block == evm.get_block(block["number"])
+    assert evm.get_block(block["number"] + 1) is None
+    assert evm.get_receipt("0x" + "00" * 32) is None
+
+
+def test_get_logs(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, int(1e18))
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    transfer = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+
+    erc20.mint.transact(bob, 10, caller=bob)
+    first = evm.mine_block()["number"]
+    erc20.transfer.transact(alice, 1, caller=bob)
+    erc20.approve.transact(alice, 1, caller=bob)
+    evm.mine_block()
+
+    assert 3 == len(evm.get_logs(address=erc20.address))
+    assert [] == evm.get_logs(address=alice)
+    assert 2 == len(evm.get_logs(topics=[transfer]))
+    # OR and wildcards, topics can be addresses
+    assert 2 == len(evm.get_logs(topics=[None, bob]))
+    assert 3 == len(evm.get_logs(topics=[None, None, [alice, bob]]))
+
+    logs = evm.get_logs(
+        address=[erc20.address],
+        topics=[transfer, None, alice],
+        from_block=first + 1,
+        abi=erc20.abi,
+    )
+    assert 1 == len(logs)
+    assert "Transfer" == logs[0]["event"]
+    (_, to, amount) = logs[0]["args"]
+    assert (alice, 1) == (to, amount)
+    assert [] == evm.get_logs(to_block=first - 1)
+
+
+def test_stub_precompile(evm, bob):
+    create_account(evm, bob, int(1e18))
+    address = "0x00000000000000000000000000000000000000f1"
+    evm.stub_precompile(address, (1234).to_bytes(32, "big"), gas_used=100)
+
+    oracle = contract_from_inline_abi(evm, ["function latestAnswer() (uint256)"])
+    oracle.at(address)
+    assert 1234 == oracle.latestAnswer.call()
+    assert 21_000 + 100 < oracle.latestAnswer.transact(caller=bob).gas_used
+
+    assert evm.remove_precompile(address)
+    assert not evm.remove_precompile(address)
+
+
+def test_python_precompile(evm, bob):
+    create_account(evm, bob, int(1e18))
+    address = "0x00000000000000000000000000000000000000f2"
+    calls = []
+
+    def price(calldata):
+        calls.append(calldata)
+        day = int.from_bytes(calldata[4:], "big")
+        if day > 10:
+            raise ValueError("no price yet")
+        return (100 + day).to_bytes(32, "big"), 500
+
+    evm.register_precompile(address, price)
+    model = contract_from_inline_abi(evm, ["function price(uint256) (uint256)"])
+    model.at(address)
+
+    assert 103 == model.price.call(3)
+    tx = model.price.transact(4, caller=bob)
+    assert 104 == tx.output
+    assert tx.gas_used > 21_000 + 500
+    assert 2 == len(calls)
+
+    with pytest.raises(RevertError) as exc:
+        model.price.transact(11, caller=bob)
+    assert "no price yet" in str(exc.value)
+    assert exc.value.gas_used < 100_000
+
+    with pytest.raises(OutOfGasError):
+        model.price.transact(5, caller=bob, gas_limit=21_500)
+
+
+def test_console_logs(evm, bob):
+    create_account(evm, bob, int(1e18))
+    console = contract_from_inline_abi(evm, ["function log(string,uint256,bool)"])
+    console.at("0x000000000000000000636F6e736F6c652e6c6f67")
+
+    assert not evm.get_console_logs()
+    assert [] == console.log.transact("count: %d", 2, True, caller=bob).console_logs
+    evm.set_console_logs(True)
+    tx = console.log.transact("count: %d", 3, True, caller=bob)
+    assert ["count: 3 true"] == tx.console_logs
+    assert ["count: 4 false"] == console.log.simulate("count: %d", 4, False, caller=bob).console_logs
+
+    evm.set_console_logs(False)
+    assert [] == console.log.transact("count: %d", 5, True, caller=bob).console_logs
+
+
+def test_coverage(evm, bob):
+    create_account(evm, bob, int(1e18))
+    address = "0x00000000000000000000000000000000000000c0"
+    # PUSH1 1 PUSH1 7 JUMPI STOP STOP JUMPDEST STOP
+    evm.set_code(address, bytes.fromhex("600160075700005b00"))
+    contract = contract_from_inline_abi(evm, ["function run()"])
+    contract.at(address)
+    sources = {
+        "address": address,
+        "source_map": "0:5:0:-;;6:5;18:5;18:5;12:5;",
+        "sources": {0: ("a.sol", "line1\nline2\nline3\nline4\n")},
+    }
+
+    assert not evm.get_coverage()
+    evm.set_coverage(True)
+    contract.run.transact(caller=bob)
+    lcov = evm.coverage_lcov([sources])
+    assert lcov.startswith("TN:\nSF:a.sol\nDA:1,1\nDA:2,1\nDA:3,1\nDA:4,0\n")
+    assert "BRDA:2,4,0,1\nBRDA:2,4,1,0\n" in lcov
+
+    evm.reset_coverage()
+    assert "LH:0\n" in evm.coverage_lcov([sources])
+
+
+def test_deploy_create2(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, int(1e18))
+    create_account(evm, alice, int(1e18))
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+    init_code, _ = erc20.abi.encode_constructor('("USD Coin", "USDC", 6)')
+    expected = evm.compute_create2_address("0x" + "00" * 31 + "01", bytes(init_code))
+
+    address = erc20.deploy("USD Coin", "USDC", 6, caller=bob, salt=1)
+    assert expected.lower() == address.lower()
+    assert erc20.name.call() == "USD Coin"
+
+    # the address doesn't depend on the caller
+    evm2 = PyEvm()
+    create_account(evm2, alice, int(1e18))
+    again = contract_from_abi_bytecode(evm2, erc20abi, erc20bin)
+    assert address == again.deploy("USD Coin", "USDC", 6, caller=alice, salt="0x01")
+
+    with pytest.raises(Exception, match="already deployed"):
+        erc20.deploy("USD Coin", "USDC", 6, caller=alice, salt=b"\x01")
+
+    nonce_address = evm.compute_create_address(bob)
+    assert nonce_address == erc20.deploy("USD Coin", "USDC", 6, caller=bob)
+    assert nonce_address == evm.compute_create_address(bob, evm.get_nonce(bob) - 1)
+
+
+def test_deploy_at(evm, bob, alice, erc20abi, erc20bin):
+    create_account(evm, bob, int(1e18))
+    usdc = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"
+    erc20 = contract_from_abi_bytecode(evm, erc20abi, erc20bin)
+
+    assert usdc == erc20.deploy_at(usdc, "USD Coin", "USDC", 6, caller=bob)
+    assert usdc == erc20.address
+    assert erc20.name.call() == "USD Coin"
+    assert erc20.owner.call() == bob
+    assert 0 == evm.get_nonce(bob)
+
+    erc20.mint.transact(alice, 10, caller=bob)
+    assert 10 == erc20.balanceOf.call(alice)
+
+    with pytest.raises(Exception, match="already deployed"):
+        erc20.deploy_at(usdc, "USD Coin", "USDC", 6, caller=bob)
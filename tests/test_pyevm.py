@@ -12,6 +12,12 @@ def test_create_account_and_balance(evm, bob):
     evm.create_account(bob, two_ether)
     assert evm.get_balance(bob) == two_ether
 
+    # balances larger than u128
+    evm.create_account(bob, 2**200)
+    assert evm.get_balance(bob) == 2**200
+    evm.set_balance(bob, 2**256 - 1)
+    assert evm.get_balance(bob) == 2**256 - 1
+
 
 def test_transfer_and_dump_state(evm, bob, alice):
     one_ether = to_wei(1, "ether")
@@ -75,3 +81,145 @@ def test_advance_block(evm, bob, block_meta_json):
 
     assert bn2 == 4  # block advanced
     assert ts2 == ts1 + 36  # timestamp advanced
+
+
+def test_checkpoint_and_revert(evm, bob, alice):
+    one_ether = to_wei(1, "ether")
+    evm.create_account(bob, to_wei(2, "ether"))
+
+    first = evm.checkpoint()
+    evm.transfer(bob, alice, one_ether)
+    second = evm.checkpoint()
+    evm.transfer(bob, alice, one_ether)
+    assert evm.get_balance(alice) == to_wei(2, "ether")
+
+    assert evm.revert_to(second)
+    assert evm.get_balance(alice) == one_ether
+
+    assert evm.revert_to(first)
+    assert evm.get_balance(alice) == 0
+    assert evm.get_balance(bob) == to_wei(2, "ether")
+
+    # both checkpoints are gone
+    assert not evm.revert_to(second)
+    assert not evm.discard(first)
+
+
+def test_select_hardfork(bob):
+    assert PyEvm().get_spec() == "latest"
+
+    evm = PyEvm(spec="Shanghai")
+    assert evm.get_spec() == "shanghai"
+    evm.create_account(bob)
+
+    # hardfork is saved in the snapshot...
+    assert PyEvm.from_snapshot(evm.create_snapshot()).get_spec() == "shanghai"
+    # ...unless overridden
+    assert PyEvm.from_snapshot(evm.create_snapshot(), spec="merge").get_spec() == "merge"
+
+    evm.set_spec("cancun")
+    assert evm.get_spec() == "cancun"
+
+    with pytest.raises(BaseException):
+        PyEvm(spec="not-a-fork")
+
+
+def test_block_config(evm, bob):
+    config = evm.get_block_config()
+    assert config["chain_id"] == 1
+    assert config["gas_limit"] == 30_000_000
+
+    evm.set_block_config(chain_id=10, coinbase=bob, basefee=7, prevrandao="0x" + "11" * 32)
+    config = evm.get_block_config()
+    assert config["chain_id"] == 10
+    assert config["coinbase"].lower() == bob
+    assert config["basefee"] == 7
+    assert config["prevrandao"] == "0x" + "11" * 32
+    assert config["gas_limit"] == 30_000_000
+
+    # saved in the snapshot
+    evm2 = PyEvm.from_snapshot(evm.create_snapshot())
+    assert evm2.get_block_config() == config
+
+    # 256-bit values
+    evm.set_block_config(difficulty=2**200)
+    assert evm.get_block_config()["difficulty"] == 2**200
+    assert evm.get_basefee() == 7
+
+
+def test_eip1559_fees(evm, bob, alice):
+    gwei = int(1e9)
+    coinbase = "0x" + "09" * 20
+    evm.create_account(bob, int(10e18))
+    evm.set_block_config(coinbase=coinbase, basefee=10 * gwei)
+
+    tx = evm.transfer(
+        bob, alice, int(1e18), max_fee_per_gas=30 * gwei, max_priority_fee_per_gas=2 * gwei
+    )
+    assert tx.gas_used == 21_000
+    assert tx.effective_gas_price == 12 * gwei
+    assert tx.fee == 21_000 * 12 * gwei
+    assert evm.get_balance(bob) == int(10e18) - int(1e18) - tx.fee
+    assert evm.get_balance(coinbase) == 21_000 * 2 * gwei
+
+    # legacy gas price below the basefee
+    with pytest.raises(BaseException):
+        evm.transfer(bob, alice, 1, gas_price=gwei)
+
+    # gas prices larger than u128
+    evm.set_balance(bob, 2**200)
+    tx = evm.transfer(bob, alice, 1, gas_price=2**130)
+    assert tx.effective_gas_price == 2**130
+    assert tx.fee == 21_000 * 2**130
+
+
+def test_fee_market(evm, bob, alice):
+    gwei = int(1e9)
+    evm.create_account(bob, int(10e18))
+    evm.set_block_config(gas_limit=42_000, basefee=gwei)
+    assert evm.get_fee_market() is None
+
+    evm.enable_fee_market()
+    assert evm.get_fee_market() == {
+        "elasticity_multiplier": 2,
+        "base_fee_max_change_denominator": 8,
+    }
+
+    # full block
+    evm.transfer(bob, alice, 1, max_fee_per_gas=2 * gwei)
+    evm.transfer(bob, alice, 1, max_fee_per_gas=2 * gwei)
+    assert evm.get_block_gas_used() == 42_000
+
+    evm.advance_block()
+    assert evm.get_block_gas_used() == 0
+    assert evm.get_basefee() == 1_125_000_000
+
+    # empty block
+    evm.advance_block()
+    assert evm.get_basefee() == 984_375_000
+
+    evm.disable_fee_market()
+    evm.advance_block()
+    assert evm.get_basefee() == 984_375_000
+
+
+def test_state_cheatcodes(evm, bob, alice):
+    # return the value in storage slot 0
+    runtime = bytes.fromhex("5f545f5260205ff3")
+    evm.create_account(bob, int(1e18))
+    evm.set_nonce(bob, 3)
+    evm.set_storage_at(bob, 0, 2**255)
+    evm.set_code(bob, runtime)
+
+    assert evm.get_balance(bob) == int(1e18)
+    assert evm.get_nonce(bob) == 3
+    assert evm.get_code(bob) == runtime
+    assert evm.get_storage_at(bob, 0) == 2**255
+
+    evm.set_balance(bob, 1)
+    assert evm.get_balance(bob) == 1
+    assert evm.get_nonce(bob) == 3
+    assert evm.get_storage_at(bob, 0) == 2**255
+
+    assert evm.get_nonce(alice) == 0
+    assert evm.get_code(alice) == b""